@@ -1,22 +1,49 @@
 use crate::{
     error::ContractError,
-    ibc::helpers::compute_voucher_token_id,
+    fees,
+    ibc::{
+        helpers::{compute_voucher_token_id, load_voucher_token_id},
+        ics29::pay_packet_fee_msg,
+        packet::{new_voucher_token_id, refund_submsg, remint_submsg},
+    },
     msg::{
-        CollectionExecuteMsg, CollectionQueryMsg, ExecuteMsg, IbcPacketMessage, InstantiateMsg,
-        QueryMsg, SudoMsg,
+        ChannelConfig, CollectionExecuteMsg, CollectionQueryMsg, Cw20HookMsg, ExecuteMsg,
+        ExpiryPolicy, ExpiryResponse, IbcPacketMessage, InstantiateMsg, JsonValue,
+        MetadataExecuteMsg, MigrateMsg, NameserviceQueryMsg, PendingClaimResponse, QueryMsg,
+        RenewalConfig, RenewalPayment, SettlementPolicy, SudoMsg, SupplyResponse, TransferMode,
+        VoucherIdScheme, VoucherResponse,
+    },
+    rate_limit,
+    state::{
+        remove_escrowed_name, save_escrowed_name, save_voucher_trace, Escrow, EscrowedToken,
+        InFlight, PaidFee, PaidRelayerFee, PendingClaim, Remint, VoucherTrace,
+        ANY_COUNTERPARTY_ALLOWED, CHANNEL_CONFIGS, CLAIMABLE_CW20_FEES, CLAIMABLE_FEES,
+        CLAIMABLE_REFUNDS, CLAIMABLE_REMINTS, CLOSED_CHANNELS, CLOSE_REQUESTS, COLLECTED_CW20_FEES,
+        COLLECTED_FEES, COLLECTION_FEES, CW20_FEES, ESCROWED_NAMES, ESCROWED_SUPPLY,
+        EXPIRY_POLICIES, FLAT_FEE, IN_FLIGHT, LEGACY_PACKETS_BEFORE, ONE_CHANNEL_PER_COUNTERPARTY,
+        PENDING_CLAIMS, PENDING_FEE_REFUNDS, PENDING_REFUNDS, REGISTRABLE_COLLECTIONS,
+        RENEWAL_CONFIGS, RENEWAL_DEPOSITS, RENEWAL_FUNDS, TELEPORT_COLLECTIONS, TELEPORT_REMOTES,
+        TREASURY, TRUSTED_COUNTERPARTIES, VOUCHERS_ADDR, VOUCHER_ID_SCHEMES, VOUCHER_SUPPLY,
+        VOUCHER_TRACES,
     },
-    state::VOUCHERS_ADDR,
 };
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Deps, DepsMut, Env, Event, IbcMsg, IbcTimeout, MessageInfo, QueryRequest,
-    QueryResponse, Response, WasmMsg, WasmQuery,
+    from_json, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Empty, Env, Event,
+    IbcMsg, IbcTimeout, MessageInfo, Order, QueryRequest, QueryResponse, Reply, Response, StdError,
+    StdResult, SubMsg, SubMsgResult, Timestamp, Uint128, WasmMsg, WasmQuery,
 };
-use cw721::msg::OwnerOfResponse;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw721::msg::{NftInfoResponse, OperatorResponse, OwnerOfResponse};
 
 type ContractResult = Result<Response, ContractError>;
 
+pub const REFUND_REPLY_ID: u64 = 1;
+pub const CALLBACK_REPLY_ID: u64 = 2;
+pub const FEE_REFUND_REPLY_ID: u64 = 3;
+const DEFAULT_SETTLEMENT_LIMIT: u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -32,6 +59,42 @@ pub fn instantiate(
     Ok(Response::default().add_event(instantiate_event))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> ContractResult {
+    let escrowed_names = msg.escrowed_names.len();
+    for escrow in msg.escrowed_names {
+        save_escrowed_name(
+            deps.storage,
+            (&escrow.channel_id, &escrow.collection, &escrow.token_id),
+            &Escrow {
+                sender_addr: escrow.sender_addr,
+            },
+        )?;
+    }
+    let vouchers = msg.vouchers.len();
+    for voucher in msg.vouchers {
+        // Vouchers minted before they were traced all got the hash id.
+        save_voucher_trace(
+            deps.storage,
+            &compute_voucher_token_id(&voucher.channel_id, &voucher.collection, &voucher.token_id),
+            &VoucherTrace {
+                channel_id: voucher.channel_id,
+                collection: voucher.collection,
+                token_id: voucher.token_id,
+                expires_at: None,
+            },
+        )?;
+    }
+    if msg.legacy_in_flight && !LEGACY_PACKETS_BEFORE.exists(deps.storage) {
+        LEGACY_PACKETS_BEFORE.save(deps.storage, &env.block.time)?;
+    }
+    let migrate_event = Event::new("my-ics-name-migrate")
+        .add_attribute("escrowed-names", escrowed_names.to_string())
+        .add_attribute("legacy-in-flight", msg.legacy_in_flight.to_string())
+        .add_attribute("vouchers", vouchers.to_string());
+    Ok(Response::default().add_event(migrate_event))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResult {
     match msg {
@@ -40,56 +103,265 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> C
             receiver_addr,
             token_id,
             channel_id,
+            refund_addr,
+            relayer_fee,
+        } => {
+            let (info, relayer_fee) = fees::split_relayer_fee(info, relayer_fee)?;
+            let fee = fees::validate_fee(deps.storage, &info, &channel_id, &collection)?;
+            execute_ibc_tranfer(
+                deps,
+                env,
+                info,
+                fee,
+                relayer_fee,
+                collection,
+                receiver_addr,
+                token_id,
+                channel_id,
+                refund_addr,
+            )
+        }
+        ExecuteMsg::IbcReturnName {
+            collection,
+            receiver_addr,
+            token_id,
+            channel_id,
+            refund_addr,
+        } => {
+            let fee = fees::validate_fee(deps.storage, &info, &channel_id, &collection)?;
+            execute_ibc_return(
+                deps,
+                env,
+                info,
+                fee,
+                collection,
+                receiver_addr,
+                token_id,
+                channel_id,
+                refund_addr,
+            )
+        }
+        ExecuteMsg::ClaimRefund {
+            collection,
+            token_id,
+            recipient,
+        } => execute_claim_refund(deps, info, collection, token_id, recipient),
+        ExecuteMsg::WithdrawFees {} => execute_withdraw_fees(deps),
+        ExecuteMsg::ClaimFees { recipient } => execute_claim_fees(deps, info, recipient),
+        ExecuteMsg::Receive(cw20_msg) => execute_receive_cw20(deps, env, info, cw20_msg),
+        ExecuteMsg::ResolveName {
+            channel_id,
+            collection,
+            token_id,
+        } => execute_resolve_name(deps, env, info, channel_id, collection, token_id),
+        ExecuteMsg::UpdateNameRecord {
+            channel_id,
+            collection,
+            token_id,
+            token_uri,
+            extension,
+        } => execute_update_name_record(
+            deps, env, info, channel_id, collection, token_id, token_uri, extension,
+        ),
+        ExecuteMsg::RegisterName {
+            channel_id,
+            collection,
+            token_id,
+            owner_addr,
+        } => execute_register_name(
+            deps, env, info, channel_id, collection, token_id, owner_addr,
+        ),
+        ExecuteMsg::RenewName {
+            channel_id,
+            collection,
+            token_id,
+        } => execute_renew_name(deps, env, info, channel_id, collection, token_id),
+        ExecuteMsg::DepositRenewal {
+            collection,
+            token_id,
+        } => execute_deposit_renewal(deps, info, collection, token_id),
+        ExecuteMsg::WithdrawRenewalDeposit {
+            collection,
+            token_id,
+        } => execute_withdraw_renewal_deposit(deps, info, collection, token_id),
+        ExecuteMsg::FundRenewals {} => execute_fund_renewals(deps, info),
+        ExecuteMsg::SyncMetadata {
+            channel_id,
+            collection,
+            token_id,
+        } => sync_metadata(deps, env, channel_id, collection, token_id),
+        ExecuteMsg::ExpireVoucher {
+            channel_id,
+            collection,
+            token_id,
+        } => execute_expire_voucher(deps, env, channel_id, collection, token_id),
+        ExecuteMsg::ClaimVoucher {
+            channel_id,
+            collection,
+            token_id,
+        } => execute_claim_voucher(deps, env, info, channel_id, collection, token_id),
+        ExecuteMsg::ReturnUnclaimed {
+            channel_id,
+            collection,
+            token_id,
+        } => execute_return_unclaimed(deps, env, channel_id, collection, token_id),
+    }
+}
+
+/// Starts a transfer or a return paid with the cw20 tokens received. The cw20 sender stands for
+/// the sender of the transfer or return.
+fn execute_receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> ContractResult {
+    let fee = fees::validate_cw20_fee(deps.storage, &info.sender, &cw20_msg)?;
+    let info = MessageInfo {
+        sender: deps.api.addr_validate(&cw20_msg.sender)?,
+        funds: vec![],
+    };
+    match from_json::<Cw20HookMsg>(&cw20_msg.msg)? {
+        Cw20HookMsg::IbcTransferName {
+            channel_id,
+            collection,
+            token_id,
+            receiver_addr,
+            refund_addr,
         } => execute_ibc_tranfer(
             deps,
             env,
             info,
+            Some(fee),
+            None,
             collection,
             receiver_addr,
             token_id,
             channel_id,
+            refund_addr,
         ),
-        ExecuteMsg::IbcReturnName {
+        Cw20HookMsg::IbcReturnName {
+            channel_id,
             collection,
-            receiver_addr,
             token_id,
-            channel_id,
+            receiver_addr,
+            refund_addr,
         } => execute_ibc_return(
             deps,
             env,
             info,
+            Some(fee),
             collection,
             receiver_addr,
             token_id,
             channel_id,
+            refund_addr,
         ),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_ibc_tranfer(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    fee: Option<PaidFee>,
+    relayer_fee: Option<PaidRelayerFee>,
     collection: String,
     receiver_addr: String,
     token_id: String,
     channel_id: String,
+    refund_addr: Option<String>,
 ) -> ContractResult {
-    validate_sender_is_owner(&deps, &info, &collection, &token_id)?;
+    validate_channel_open(&deps, &channel_id)?;
+    validate_receiver_prefix(&deps, &channel_id, &receiver_addr)?;
+    let refund_addr = validate_refund_addr(&deps, refund_addr)?;
+    let teleported = TELEPORT_COLLECTIONS.has(deps.storage, (&channel_id, &collection));
+    if !teleported {
+        validate_supply_cap(&deps, &channel_id)?;
+    }
+    let owner = validate_sender_is_owner_or_approved(&deps, &info, &collection, &token_id)?;
+    let expires_at = query_expiry(&deps, &collection, &token_id)?;
+    rate_limit::consume_outbound(deps.storage, env.block.time, &channel_id, &info.sender)?;
+    let (mode, token) = if teleported {
+        let nft_info =
+            deps.querier
+                .query::<NftInfoResponse<Option<JsonValue>>>(&QueryRequest::Wasm(
+                    WasmQuery::Smart {
+                        contract_addr: collection.to_owned(),
+                        msg: to_json_binary(&CollectionQueryMsg::NftInfo {
+                            token_id: token_id.to_owned(),
+                        })?,
+                    },
+                ))?;
+        (
+            TransferMode::Teleport {
+                token_uri: nft_info.token_uri.to_owned(),
+                extension: nft_info.extension.to_owned(),
+            },
+            EscrowedToken::Teleported {
+                token_uri: nft_info.token_uri,
+                extension: nft_info.extension,
+            },
+        )
+    } else {
+        save_escrowed_name(
+            deps.storage,
+            (&channel_id, &collection, &token_id),
+            &Escrow {
+                sender_addr: owner.to_owned(),
+            },
+        )?;
+        (TransferMode::Escrow, EscrowedToken::Name)
+    };
+    IN_FLIGHT.save(
+        deps.storage,
+        (&channel_id, &collection, &token_id),
+        &InFlight {
+            token,
+            refund_addr: refund_addr.to_owned().unwrap_or_else(|| owner.to_owned()),
+            sent_at: env.block.time,
+            fee,
+            relayer_fee: relayer_fee.to_owned(),
+        },
+    )?;
     let escrow_msg = CollectionExecuteMsg::TransferNft {
         recipient: env.contract.address.to_string(),
         token_id: token_id.to_owned(),
     };
+    let pay_fee_msg = relayer_fee.map(|paid| {
+        pay_packet_fee_msg(
+            &paid.fee,
+            &format!("wasm.{}", env.contract.address),
+            &channel_id,
+            env.contract.address.as_str(),
+        )
+    });
     let escrow_exec_msg = WasmMsg::Execute {
         contract_addr: collection.to_owned(),
         msg: to_json_binary(&escrow_msg)?,
         funds: vec![],
     };
+    // A teleported name is escrowed first, as the contract can only burn its own tokens.
+    let burn_exec_msg = if teleported {
+        Some(WasmMsg::Execute {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::Burn {
+                token_id: token_id.to_owned(),
+            })?,
+            funds: vec![],
+        })
+    } else {
+        None
+    };
     let transfer_msg = IbcPacketMessage::TransferName {
         collection: collection.to_owned(),
         token_id,
-        sender_addr: info.sender.to_string(),
+        sender_addr: owner,
         receiver_addr,
+        refund_addr,
+        expires_at,
+        mode,
     };
     let transfer_packet = IbcMsg::SendPacket {
         channel_id,
@@ -98,21 +370,43 @@ fn execute_ibc_tranfer(
     };
     Ok(Response::default()
         .add_message(escrow_exec_msg)
+        .add_messages(burn_exec_msg)
+        .add_messages(pay_fee_msg)
         .add_message(transfer_packet))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_ibc_return(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    fee: Option<PaidFee>,
     collection: String,
     receiver_addr: String,
     token_id: String,
     channel_id: String,
+    refund_addr: Option<String>,
 ) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    validate_receiver_prefix(&deps, &channel_id, &receiver_addr)?;
+    let refund_addr = validate_refund_addr(&deps, refund_addr)?;
     let voucher_collection: String = VOUCHERS_ADDR.load(deps.storage)?;
-    let voucher_token_id = compute_voucher_token_id(&channel_id, &collection, &token_id);
-    validate_sender_is_owner(&deps, &info, &voucher_collection, &voucher_token_id)?;
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let owner =
+        validate_sender_is_owner_or_approved(&deps, &info, &voucher_collection, &voucher_token_id)?;
+    rate_limit::consume_outbound(deps.storage, env.block.time, &channel_id, &info.sender)?;
+    IN_FLIGHT.save(
+        deps.storage,
+        (&channel_id, &collection, &token_id),
+        &InFlight {
+            token: EscrowedToken::Voucher,
+            refund_addr: refund_addr.to_owned().unwrap_or_else(|| owner.to_owned()),
+            sent_at: env.block.time,
+            fee,
+            relayer_fee: None,
+        },
+    )?;
     let escrow_msg = CollectionExecuteMsg::TransferNft {
         recipient: env.contract.address.to_string(),
         token_id: voucher_token_id,
@@ -129,8 +423,9 @@ fn execute_ibc_return(
     let return_msg = IbcPacketMessage::ReturnName {
         collection,
         token_id,
-        sender_addr: info.sender.to_string(),
+        sender_addr: owner,
         receiver_addr: receiver_addr.to_owned(),
+        refund_addr,
     };
     let return_packet = IbcMsg::SendPacket {
         channel_id,
@@ -143,222 +438,2759 @@ fn execute_ibc_return(
         .add_message(return_packet))
 }
 
-pub fn validate_sender_is_owner(
-    deps: &DepsMut,
-    info: &MessageInfo,
-    collection: &String,
-    token_id: &String,
-) -> Result<(), ContractError> {
-    let owner = deps
-        .querier
-        .query::<OwnerOfResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: collection.to_string(),
-            msg: to_json_binary(&CollectionQueryMsg::OwnerOf {
-                token_id: token_id.to_string(),
-                include_expired: None,
-            })?,
-        }))?
-        .owner;
-    if owner != info.sender.to_string() {
-        Err(ContractError::OnlyOwner)
-    } else {
-        Ok(())
-    }
+fn execute_resolve_name(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let resolve_msg = IbcPacketMessage::ResolveName {
+        collection,
+        token_id,
+        requester: info.sender.to_string(),
+    };
+    let resolve_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&resolve_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default().add_message(resolve_packet))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> Result<QueryResponse, ContractError> {
-    todo!("query");
+#[allow(clippy::too_many_arguments)]
+fn execute_update_name_record(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    token_uri: Option<String>,
+    extension: Option<JsonValue>,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let voucher_collection: String = VOUCHERS_ADDR.load(deps.storage)?;
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let owner =
+        validate_sender_is_owner_or_approved(&deps, &info, &voucher_collection, &voucher_token_id)?;
+    let update_msg = IbcPacketMessage::UpdateNameRecord {
+        collection,
+        token_id,
+        sender_addr: owner,
+        token_uri,
+        extension,
+    };
+    let update_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&update_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default().add_message(update_packet))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> ContractResult {
-    match msg {
-        SudoMsg::UpdateVouchersAddr(vouchers_addr) => {
-            sudo_update_vouchers_addr(deps, &vouchers_addr)
-        }
-    }
+fn execute_register_name(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    owner_addr: Option<String>,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let owner_addr = owner_addr.unwrap_or_else(|| info.sender.to_string());
+    let owner_addr =
+        deps.api
+            .addr_validate(&owner_addr)
+            .map_err(|_| ContractError::InvalidReceiver {
+                receiver_addr: owner_addr.to_owned(),
+            })?;
+    new_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let register_msg = IbcPacketMessage::RegisterName {
+        collection,
+        token_id,
+        owner_addr: owner_addr.to_string(),
+    };
+    let register_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&register_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default().add_message(register_packet))
 }
 
-fn sudo_update_vouchers_addr(deps: DepsMut, vouchers_addr: &Option<String>) -> ContractResult {
-    if let Some(addr) = vouchers_addr {
-        VOUCHERS_ADDR.save(deps.storage, &addr)?;
-    }
-    let sudo_event = Event::new("my-ics-name");
-    let sudo_event = append_vouchers_attributes(sudo_event, vouchers_addr);
-    Ok(Response::default().add_event(sudo_event))
+fn execute_renew_name(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let voucher_collection: String = VOUCHERS_ADDR.load(deps.storage)?;
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let owner =
+        validate_sender_is_owner_or_approved(&deps, &info, &voucher_collection, &voucher_token_id)?;
+    let renew_msg = IbcPacketMessage::RenewName {
+        collection,
+        token_id,
+        sender_addr: owner,
+    };
+    let renew_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&renew_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default().add_message(renew_packet))
 }
 
-fn append_vouchers_attributes(my_event: Event, vouchers_addr: &Option<String>) -> Event {
-    if let Some(addr) = vouchers_addr {
-        my_event.add_attribute("update-vouchers-address", addr)
-    } else {
-        my_event
+fn execute_deposit_renewal(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    let config = RENEWAL_CONFIGS
+        .may_load(deps.storage, &collection)?
+        .filter(|config| config.payment == RenewalPayment::Deposit)
+        .ok_or_else(|| ContractError::RenewalNotAllowed {
+            collection: collection.to_owned(),
+        })?;
+    let [deposit] = info.funds.as_slice() else {
+        return Err(ContractError::InvalidDeposit {
+            denom: config.price.denom,
+        });
+    };
+    if deposit.denom != config.price.denom {
+        return Err(ContractError::InvalidDeposit {
+            denom: config.price.denom,
+        });
     }
+    let key = (collection.as_str(), token_id.as_str(), info.sender.as_str());
+    let deposited = match RENEWAL_DEPOSITS.may_load(deps.storage, key)? {
+        // Deposits made before the price denom changed are withdrawn before adding to them.
+        Some(deposited) if deposited.denom != deposit.denom => {
+            return Err(ContractError::InvalidDeposit {
+                denom: deposited.denom,
+            })
+        }
+        Some(deposited) => Coin {
+            denom: deposited.denom,
+            amount: deposited.amount.checked_add(deposit.amount)?,
+        },
+        None => deposit.to_owned(),
+    };
+    RENEWAL_DEPOSITS.save(deps.storage, key, &deposited)?;
+    let deposit_event = Event::new("my-ics-name-renewal-deposit")
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("amount", deposit.to_string())
+        .add_attribute("deposited", deposited.to_string());
+    Ok(Response::default().add_event(deposit_event))
 }
 
-#[cfg(test)]
-mod tests {
-    use std::marker::PhantomData;
+fn execute_withdraw_renewal_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    let key = (collection.as_str(), token_id.as_str(), info.sender.as_str());
+    let withdrawn = RENEWAL_DEPOSITS
+        .may_load(deps.storage, key)?
+        .ok_or_else(|| ContractError::NoRenewalDeposit {
+            collection: collection.to_owned(),
+            token_id: token_id.to_owned(),
+        })?;
+    RENEWAL_DEPOSITS.remove(deps.storage, key);
+    let depositor = info.sender.to_string();
+    let withdraw_event = Event::new("my-ics-name-renewal-withdraw")
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("depositor", &depositor)
+        .add_attribute("amount", withdrawn.to_string());
+    Ok(Response::default()
+        .add_message(BankMsg::Send {
+            to_address: depositor,
+            amount: vec![withdrawn],
+        })
+        .add_event(withdraw_event))
+}
 
-    use cosmwasm_std::{
-        from_json,
-        testing::{self, MockApi, MockQuerier, MockStorage},
-        to_json_binary, Addr, ContractResult, Empty, Event, IbcMsg, IbcTimeout, OwnedDeps, Querier,
-        QuerierResult, QueryRequest, Response, SystemError, SystemResult, WasmMsg, WasmQuery,
+fn execute_fund_renewals(deps: DepsMut, info: MessageInfo) -> ContractResult {
+    let [funds] = info.funds.as_slice() else {
+        return Err(ContractError::InvalidRenewalFunds);
     };
-    use cw721::msg::OwnerOfResponse;
+    let funded = RENEWAL_FUNDS.update(deps.storage, &funds.denom, |funded| -> StdResult<_> {
+        Ok(funded.unwrap_or_default().checked_add(funds.amount)?)
+    })?;
+    let fund_event = Event::new("my-ics-name-renewal-fund")
+        .add_attribute("amount", funds.to_string())
+        .add_attribute("funded", funded.to_string());
+    Ok(Response::default().add_event(fund_event))
+}
 
-    use crate::{
-        msg::{
-            CollectionExecuteMsg, CollectionQueryMsg, ExecuteMsg, IbcPacketMessage, InstantiateMsg,
-        },
-        state::VOUCHERS_ADDR,
+/// Sends the metadata of an escrowed name, as currently held by its collection.
+fn sync_metadata(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    if !ESCROWED_NAMES.has(deps.storage, (&channel_id, &collection, &token_id)) {
+        return Err(ContractError::NameNotEscrowed {
+            collection,
+            token_id,
+        });
+    }
+    let nft_info =
+        deps.querier
+            .query::<NftInfoResponse<Option<JsonValue>>>(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: collection.to_owned(),
+                msg: to_json_binary(&CollectionQueryMsg::NftInfo {
+                    token_id: token_id.to_owned(),
+                })?,
+            }))?;
+    let sync_msg = IbcPacketMessage::SyncMetadata {
+        collection,
+        token_id,
+        token_uri: nft_info.token_uri,
+        extension: nft_info.extension,
+    };
+    let sync_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&sync_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
     };
+    Ok(Response::default().add_message(sync_packet))
+}
 
-    pub fn mock_deps(
-        response: OwnerOfResponse,
-    ) -> OwnedDeps<MockStorage, MockApi, OwnerOfMockQuerier, Empty> {
-        OwnedDeps {
-            storage: MockStorage::default(),
-            api: MockApi::default(),
-            querier: OwnerOfMockQuerier::new(MockQuerier::new(&[]), response),
-            custom_query_type: PhantomData,
-        }
+fn execute_expire_voucher(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let trace = VOUCHER_TRACES
+        .may_load(deps.storage, &voucher_token_id)?
+        .ok_or_else(|| ContractError::VoucherNotFound {
+            voucher_token_id: voucher_token_id.to_owned(),
+        })?;
+    match trace.expires_at {
+        Some(expires_at) if expires_at <= env.block.time => {}
+        _ => return Err(ContractError::VoucherNotExpired { voucher_token_id }),
     }
-
-    pub struct OwnerOfMockQuerier {
-        base: MockQuerier,
-        response: OwnerOfResponse,
+    if IN_FLIGHT.has(deps.storage, (&channel_id, &collection, &token_id)) {
+        return Err(ContractError::VoucherInFlight { voucher_token_id });
     }
+    let expire_msg = IbcPacketMessage::ExpireVoucher {
+        collection,
+        token_id,
+    };
+    let expire_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&expire_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default().add_message(expire_packet))
+}
 
-    impl Querier for OwnerOfMockQuerier {
-        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
-            let request: QueryRequest<Empty> = match from_json(bin_request) {
-                Ok(v) => v,
-                Err(e) => {
-                    return SystemResult::Err(SystemError::InvalidRequest {
-                        error: format!("Parsing query request: {}", e),
-                        request: bin_request.into(),
-                    })
-                }
-            };
-
-            self.handle_query(&request)
-        }
+fn execute_claim_voucher(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let claim = load_pending_claim(&deps, &voucher_token_id)?;
+    if info.sender != claim.receiver_addr {
+        return Err(ContractError::OnlyClaimReceiver { voucher_token_id });
     }
-
-    impl OwnerOfMockQuerier {
-        pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
-            match &request {
-                QueryRequest::Wasm(WasmQuery::Smart {
-                    contract_addr: _,
-                    msg,
-                }) => {
-                    let expected = to_json_binary(&CollectionQueryMsg::OwnerOf {
-                        token_id: "3".to_owned(),
-                        include_expired: None,
-                    })
-                    .expect("Failed to create expected query");
-                    assert_eq!(expected.to_vec(), msg.to_vec(), "Query is not owner");
-                    SystemResult::Ok(ContractResult::Ok(
-                        to_json_binary(&self.response).expect("Failed to serialize owner response"),
-                    ))
-                }
-                _ => self.base.handle_query(request),
+    if claim.claim_until <= env.block.time {
+        return Err(ContractError::ClaimWindowPassed { voucher_token_id });
+    }
+    PENDING_CLAIMS.remove(deps.storage, &voucher_token_id);
+    let claim_msg = CollectionExecuteMsg::TransferNft {
+        recipient: claim.receiver_addr.to_owned(),
+        token_id: voucher_token_id,
+    };
+    let claim_exec_msg = WasmMsg::Execute {
+        contract_addr: VOUCHERS_ADDR.load(deps.storage)?,
+        msg: to_json_binary(&claim_msg)?,
+        funds: vec![],
+    };
+    let claim_event = Event::new("my-ics-name-voucher-claim")
+        .add_attribute("channel", channel_id)
+        .add_attribute("original-collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("receiver", claim.receiver_addr);
+    Ok(Response::default()
+        .add_message(claim_exec_msg)
+        .add_event(claim_event))
+}
+
+/// The voucher stays in the inbox while the return is in flight. Should the return fail, it
+/// remains there and can be returned again.
+fn execute_return_unclaimed(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    let claim = load_pending_claim(&deps, &voucher_token_id)?;
+    if claim.claim_until > env.block.time {
+        return Err(ContractError::ClaimWindowOpen { voucher_token_id });
+    }
+    let in_flight_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+    if IN_FLIGHT.has(deps.storage, in_flight_key) {
+        return Err(ContractError::VoucherInFlight { voucher_token_id });
+    }
+    IN_FLIGHT.save(
+        deps.storage,
+        in_flight_key,
+        &InFlight {
+            token: EscrowedToken::Voucher,
+            refund_addr: env.contract.address.to_string(),
+            sent_at: env.block.time,
+            fee: None,
+            relayer_fee: None,
+        },
+    )?;
+    let return_event = Event::new("my-ics-name-unclaimed-return")
+        .add_attribute("channel", channel_id.to_owned())
+        .add_attribute("original-collection", collection.to_owned())
+        .add_attribute("token-id", token_id.to_owned())
+        .add_attribute("receiver", claim.return_addr.to_owned());
+    let return_msg = IbcPacketMessage::ReturnName {
+        collection,
+        token_id,
+        sender_addr: env.contract.address.to_string(),
+        receiver_addr: claim.return_addr,
+        refund_addr: None,
+    };
+    let return_packet = IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&return_msg)?,
+        timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(120)),
+    };
+    Ok(Response::default()
+        .add_event(return_event)
+        .add_message(return_packet))
+}
+
+fn load_pending_claim(
+    deps: &DepsMut,
+    voucher_token_id: &str,
+) -> Result<PendingClaim, ContractError> {
+    PENDING_CLAIMS
+        .may_load(deps.storage, voucher_token_id)?
+        .ok_or_else(|| ContractError::NoPendingClaim {
+            voucher_token_id: voucher_token_id.to_owned(),
+        })
+}
+
+fn execute_claim_refund(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    token_id: String,
+    recipient: Option<String>,
+) -> ContractResult {
+    let refund_recipient = load_claimable_refund(&deps, &collection, &token_id)?;
+    if info.sender != refund_recipient {
+        return Err(ContractError::OnlyRefundRecipient);
+    }
+    claim_refund(
+        deps,
+        collection,
+        token_id,
+        recipient.unwrap_or(refund_recipient),
+    )
+}
+
+fn load_claimable_refund(
+    deps: &DepsMut,
+    collection: &str,
+    token_id: &str,
+) -> Result<String, ContractError> {
+    CLAIMABLE_REFUNDS
+        .may_load(deps.storage, (collection, token_id))?
+        .ok_or_else(|| ContractError::NoClaimableRefund {
+            collection: collection.to_owned(),
+            token_id: token_id.to_owned(),
+        })
+}
+
+fn claim_refund(
+    deps: DepsMut,
+    collection: String,
+    token_id: String,
+    recipient: String,
+) -> ContractResult {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let key = (collection.as_str(), token_id.as_str());
+    CLAIMABLE_REFUNDS.remove(deps.storage, key);
+    let claim_exec_msg = match CLAIMABLE_REMINTS.may_load(deps.storage, key)? {
+        Some(remint) => {
+            CLAIMABLE_REMINTS.remove(deps.storage, key);
+            remint_teleported_msg(
+                &collection,
+                &token_id,
+                recipient.as_str(),
+                remint.token_uri,
+                remint.extension,
+            )?
+        }
+        None => WasmMsg::Execute {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: recipient.to_string(),
+                token_id: token_id.to_owned(),
+            })?,
+            funds: vec![],
+        },
+    };
+    let claim_event = Event::new("my-ics-name-refund-claim")
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("recipient", recipient);
+    Ok(Response::default()
+        .add_message(claim_exec_msg)
+        .add_event(claim_event))
+}
+
+fn execute_withdraw_fees(deps: DepsMut) -> ContractResult {
+    let treasury = TREASURY
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoTreasury)?;
+    let collected = COLLECTED_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let collected_cw20 = COLLECTED_CW20_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if collected.is_empty() && collected_cw20.is_empty() {
+        return Err(ContractError::NoFeesCollected);
+    }
+    let amount = collected
+        .into_iter()
+        .map(|(denom, amount)| {
+            COLLECTED_FEES.remove(deps.storage, &denom);
+            Coin { denom, amount }
+        })
+        .collect::<Vec<_>>();
+    let mut withdrawn = amount.iter().map(Coin::to_string).collect::<Vec<_>>();
+    let mut withdraw_msgs = vec![];
+    if !amount.is_empty() {
+        withdraw_msgs.push(CosmosMsg::from(BankMsg::Send {
+            to_address: treasury.to_owned(),
+            amount,
+        }));
+    }
+    for (cw20_addr, amount) in collected_cw20 {
+        COLLECTED_CW20_FEES.remove(deps.storage, &cw20_addr);
+        withdrawn.push(format!("{amount}{cw20_addr}"));
+        withdraw_msgs.push(
+            WasmMsg::Execute {
+                contract_addr: cw20_addr,
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: treasury.to_owned(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        );
+    }
+    let withdraw_event = Event::new("my-ics-name-fees-withdraw")
+        .add_attribute("treasury", treasury)
+        .add_attribute("amount", withdrawn.join(","));
+    Ok(Response::default()
+        .add_messages(withdraw_msgs)
+        .add_event(withdraw_event))
+}
+
+fn execute_claim_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> ContractResult {
+    let payer = info.sender.as_str();
+    let recipient = recipient.unwrap_or_else(|| payer.to_owned());
+    let claimable = CLAIMABLE_FEES
+        .prefix(payer)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let claimable_cw20 = CLAIMABLE_CW20_FEES
+        .prefix(payer)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if claimable.is_empty() && claimable_cw20.is_empty() {
+        return Err(ContractError::NoClaimableFees);
+    }
+    let amount = claimable
+        .into_iter()
+        .map(|(denom, amount)| {
+            CLAIMABLE_FEES.remove(deps.storage, (payer, &denom));
+            Coin { denom, amount }
+        })
+        .collect::<Vec<_>>();
+    let mut claimed = amount.iter().map(Coin::to_string).collect::<Vec<_>>();
+    let mut claim_msgs = vec![];
+    if !amount.is_empty() {
+        claim_msgs.push(CosmosMsg::from(BankMsg::Send {
+            to_address: recipient.to_owned(),
+            amount,
+        }));
+    }
+    for (cw20_addr, amount) in claimable_cw20 {
+        CLAIMABLE_CW20_FEES.remove(deps.storage, (payer, &cw20_addr));
+        claimed.push(format!("{amount}{cw20_addr}"));
+        claim_msgs.push(
+            WasmMsg::Execute {
+                contract_addr: cw20_addr,
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_owned(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        );
+    }
+    let claim_event = Event::new("my-ics-name-fees-claim")
+        .add_attribute("payer", payer)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", claimed.join(","));
+    Ok(Response::default()
+        .add_messages(claim_msgs)
+        .add_event(claim_event))
+}
+
+pub fn validate_sender_is_owner_or_approved(
+    deps: &DepsMut,
+    info: &MessageInfo,
+    collection: &String,
+    token_id: &String,
+) -> Result<String, ContractError> {
+    let owner_of =
+        deps.querier
+            .query::<OwnerOfResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: collection.to_string(),
+                msg: to_json_binary(&CollectionQueryMsg::OwnerOf {
+                    token_id: token_id.to_string(),
+                    include_expired: None,
+                })?,
+            }))?;
+    if info.sender == owner_of.owner
+        || owner_of
+            .approvals
+            .iter()
+            .any(|approval| approval.spender == info.sender)
+        || is_operator(deps, collection, &owner_of.owner, &info.sender)?
+    {
+        Ok(owner_of.owner)
+    } else {
+        Err(ContractError::OnlyOwner)
+    }
+}
+
+fn is_operator(
+    deps: &DepsMut,
+    collection: &str,
+    owner: &str,
+    operator: &Addr,
+) -> Result<bool, ContractError> {
+    let operator_query = to_json_binary(&CollectionQueryMsg::Operator {
+        owner: owner.to_string(),
+        operator: operator.to_string(),
+        include_expired: None,
+    })?;
+    let operator = deps
+        .querier
+        .query::<OperatorResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: collection.to_string(),
+            msg: operator_query,
+        }));
+    match operator {
+        Ok(_) => Ok(true),
+        // Collections answer with a not found error when there is no such operator, any other
+        // error, e.g. a wrong collection address, is not a refusal.
+        Err(StdError::NotFound { .. }) => Ok(false),
+        Err(StdError::GenericErr { msg, .. }) if msg.contains("not found") => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Expiry of a name as told by its collection. Collections that do not answer the query have
+/// names that never expire.
+pub(crate) fn query_expiry(
+    deps: &DepsMut,
+    collection: &str,
+    token_id: &str,
+) -> StdResult<Option<Timestamp>> {
+    let expiry_query = to_json_binary(&NameserviceQueryMsg::Expiry {
+        token_id: token_id.to_owned(),
+    })?;
+    Ok(deps
+        .querier
+        .query::<ExpiryResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: collection.to_owned(),
+            msg: expiry_query,
+        }))
+        .ok()
+        .and_then(|expiry| expiry.expires_at))
+}
+
+/// Mints back a teleported name whose packet did not go through.
+pub(crate) fn remint_teleported_msg(
+    collection: &str,
+    token_id: &str,
+    recipient: &str,
+    token_uri: Option<String>,
+    extension: Option<JsonValue>,
+) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: collection.to_owned(),
+        msg: to_json_binary(&MetadataExecuteMsg::Mint {
+            token_id: token_id.to_owned(),
+            owner: recipient.to_owned(),
+            token_uri,
+            extension,
+        })?,
+        funds: vec![],
+    })
+}
+
+fn validate_channel_open(deps: &DepsMut, channel_id: &str) -> Result<(), ContractError> {
+    if CLOSED_CHANNELS.has(deps.storage, channel_id) {
+        Err(ContractError::ChannelClosed {
+            channel_id: channel_id.to_owned(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_supply_cap(deps: &DepsMut, channel_id: &str) -> Result<(), ContractError> {
+    let config = CHANNEL_CONFIGS
+        .may_load(deps.storage, channel_id)?
+        .unwrap_or_default();
+    if let Some(supply_cap) = config.supply_cap {
+        let escrowed = ESCROWED_SUPPLY
+            .prefix(channel_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, count)| count))
+            .sum::<StdResult<u64>>()?;
+        if escrowed >= supply_cap {
+            return Err(ContractError::SupplyCapReached {
+                channel_id: channel_id.to_owned(),
+                supply_cap,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_refund_addr(
+    deps: &DepsMut,
+    refund_addr: Option<String>,
+) -> Result<Option<String>, ContractError> {
+    refund_addr
+        .map(|addr| match deps.api.addr_validate(&addr) {
+            Ok(validated) => Ok(validated.to_string()),
+            Err(_) => Err(ContractError::InvalidRefundAddr { refund_addr: addr }),
+        })
+        .transpose()
+}
+
+fn validate_receiver_prefix(
+    deps: &DepsMut,
+    channel_id: &str,
+    receiver_addr: &str,
+) -> Result<(), ContractError> {
+    let config = CHANNEL_CONFIGS
+        .may_load(deps.storage, channel_id)?
+        .unwrap_or_default();
+    match config.bech32_prefix {
+        Some(prefix) if !receiver_addr.starts_with(&format!("{prefix}1")) => {
+            Err(ContractError::InvalidReceiverPrefix {
+                receiver_addr: receiver_addr.to_owned(),
+                expected: prefix,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> ContractResult {
+    match msg.id {
+        REFUND_REPLY_ID => reply_refund(deps, msg.result),
+        CALLBACK_REPLY_ID => Ok(reply_callback(msg.result)),
+        FEE_REFUND_REPLY_ID => reply_fee_refund(deps, msg.result),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+fn reply_refund(deps: DepsMut, result: SubMsgResult) -> ContractResult {
+    let refund = PENDING_REFUNDS
+        .pop_front(deps.storage)?
+        .ok_or(ContractError::NoPendingRefund)?;
+    match result {
+        SubMsgResult::Ok(_) => Ok(Response::default()),
+        SubMsgResult::Err(error) => {
+            let key = (refund.contract_addr.as_str(), refund.token_id.as_str());
+            CLAIMABLE_REFUNDS.save(deps.storage, key, &refund.recipient)?;
+            if let Some(remint) = &refund.remint {
+                CLAIMABLE_REMINTS.save(deps.storage, key, remint)?;
+            }
+            let claimable_event = Event::new("my-ics-name-refund-claimable")
+                .add_attribute("collection", refund.contract_addr)
+                .add_attribute("token-id", refund.token_id)
+                .add_attribute("recipient", refund.recipient)
+                .add_attribute("error", error);
+            Ok(Response::default().add_event(claimable_event))
+        }
+    }
+}
+
+/// Fee refunds are sent with `reply_always`, in the order of `PENDING_FEE_REFUNDS`, so that a
+/// failing payment does not fail the acknowledgement. A failed refund becomes claimable by its
+/// payer.
+fn reply_fee_refund(deps: DepsMut, result: SubMsgResult) -> ContractResult {
+    let fee = PENDING_FEE_REFUNDS
+        .pop_front(deps.storage)?
+        .ok_or(ContractError::NoPendingRefund)?;
+    match result {
+        SubMsgResult::Ok(_) => Ok(Response::default()),
+        SubMsgResult::Err(error) => {
+            let claimable_event = Event::new("my-ics-name-fee-refund-claimable")
+                .add_attribute("payer", &fee.payer)
+                .add_attribute("error", error);
+            fees::keep_claimable_fee(deps.storage, fee)?;
+            Ok(Response::default().add_event(claimable_event))
+        }
+    }
+}
+
+/// Callbacks are sent with `reply_on_error`, so that a failing requester cannot fail the
+/// acknowledgement. Only their failures are replied to, and recorded in an event.
+fn reply_callback(result: SubMsgResult) -> Response {
+    match result {
+        SubMsgResult::Ok(_) => Response::default(),
+        SubMsgResult::Err(error) => Response::default()
+            .add_event(Event::new("my-ics-name-callback-failed").add_attribute("error", error)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<QueryResponse, ContractError> {
+    match msg {
+        QueryMsg::Supply {
+            channel_id,
+            collection,
+        } => Ok(to_json_binary(&query_supply(
+            deps, channel_id, collection,
+        )?)?),
+        QueryMsg::Voucher { voucher_token_id } => {
+            Ok(to_json_binary(&query_voucher(deps, voucher_token_id)?)?)
+        }
+        QueryMsg::PendingClaim { voucher_token_id } => Ok(to_json_binary(&query_pending_claim(
+            deps,
+            voucher_token_id,
+        )?)?),
+    }
+}
+
+fn query_supply(
+    deps: Deps,
+    channel_id: String,
+    collection: String,
+) -> Result<SupplyResponse, ContractError> {
+    let supply_key = (channel_id.as_str(), collection.as_str());
+    Ok(SupplyResponse {
+        escrowed: ESCROWED_SUPPLY
+            .may_load(deps.storage, supply_key)?
+            .unwrap_or_default(),
+        vouchers: VOUCHER_SUPPLY
+            .may_load(deps.storage, supply_key)?
+            .unwrap_or_default(),
+    })
+}
+
+fn query_voucher(deps: Deps, voucher_token_id: String) -> Result<VoucherResponse, ContractError> {
+    let trace = VOUCHER_TRACES
+        .may_load(deps.storage, &voucher_token_id)?
+        .ok_or(ContractError::VoucherNotFound { voucher_token_id })?;
+    Ok(VoucherResponse {
+        channel_id: trace.channel_id,
+        collection: trace.collection,
+        token_id: trace.token_id,
+        expires_at: trace.expires_at,
+    })
+}
+
+fn query_pending_claim(
+    deps: Deps,
+    voucher_token_id: String,
+) -> Result<PendingClaimResponse, ContractError> {
+    let claim = PENDING_CLAIMS
+        .may_load(deps.storage, &voucher_token_id)?
+        .ok_or(ContractError::NoPendingClaim { voucher_token_id })?;
+    Ok(PendingClaimResponse {
+        receiver_addr: claim.receiver_addr,
+        claim_until: claim.claim_until,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> ContractResult {
+    match msg {
+        SudoMsg::UpdateVouchersAddr(vouchers_addr) => {
+            sudo_update_vouchers_addr(deps, &vouchers_addr)
+        }
+        SudoMsg::UpdateChannelConfig { channel_id, config } => {
+            sudo_update_channel_config(deps, channel_id, config)
+        }
+        SudoMsg::ClaimRefund {
+            collection,
+            token_id,
+            recipient,
+        } => sudo_claim_refund(deps, collection, token_id, recipient),
+        SudoMsg::ForceUnescrow {
+            channel_id,
+            collection,
+            token_id,
+            recipient,
+        } => sudo_force_unescrow(deps, channel_id, collection, token_id, recipient),
+        SudoMsg::SettleChannel {
+            channel_id,
+            policy,
+            limit,
+        } => sudo_settle_channel(deps, env, channel_id, policy, limit),
+        SudoMsg::CloseChannel { channel_id } => sudo_close_channel(deps, env, channel_id),
+        SudoMsg::UpdateTrustedCounterparty {
+            connection_id,
+            port_id,
+            trusted,
+        } => sudo_update_trusted_counterparty(deps, connection_id, port_id, trusted),
+        SudoMsg::UpdateAnyCounterpartyAllowed(allowed) => {
+            sudo_update_any_counterparty_allowed(deps, allowed)
+        }
+        SudoMsg::UpdateOneChannelPerCounterparty(one_channel) => {
+            sudo_update_one_channel_per_counterparty(deps, one_channel)
+        }
+        SudoMsg::UpdateFlatFee(fee) => sudo_update_flat_fee(deps, fee),
+        SudoMsg::UpdateCollectionFee { collection, fee } => {
+            sudo_update_collection_fee(deps, collection, fee)
+        }
+        SudoMsg::UpdateTreasury(treasury) => sudo_update_treasury(deps, treasury),
+        SudoMsg::UpdateCw20Fee { cw20_addr, fee } => sudo_update_cw20_fee(deps, cw20_addr, fee),
+        SudoMsg::UpdateRegistrableCollection {
+            collection,
+            registrable,
+        } => sudo_update_registrable_collection(deps, collection, registrable),
+        SudoMsg::UpdateRenewalConfig { collection, config } => {
+            sudo_update_renewal_config(deps, collection, config)
+        }
+        SudoMsg::SyncMetadata {
+            channel_id,
+            collection,
+            token_id,
+        } => sync_metadata(deps, env, channel_id, collection, token_id),
+        SudoMsg::UpdateExpiryPolicy { collection, policy } => {
+            sudo_update_expiry_policy(deps, collection, policy)
+        }
+        SudoMsg::UpdateTeleportCollection {
+            channel_id,
+            collection,
+            remote_collection,
+        } => sudo_update_teleport_collection(deps, channel_id, collection, remote_collection),
+        SudoMsg::UpdateVoucherIdScheme {
+            channel_id,
+            collection,
+            scheme,
+        } => sudo_update_voucher_id_scheme(deps, channel_id, collection, scheme),
+    }
+}
+
+fn sudo_update_vouchers_addr(deps: DepsMut, vouchers_addr: &Option<String>) -> ContractResult {
+    if let Some(addr) = vouchers_addr {
+        VOUCHERS_ADDR.save(deps.storage, &addr)?;
+    }
+    let sudo_event = Event::new("my-ics-name");
+    let sudo_event = append_vouchers_attributes(sudo_event, vouchers_addr);
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_channel_config(
+    deps: DepsMut,
+    channel_id: String,
+    config: ChannelConfig,
+) -> ContractResult {
+    CHANNEL_CONFIGS.save(deps.storage, &channel_id, &config)?;
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-channel-config", channel_id)
+        .add_attribute("bech32-prefix", config.bech32_prefix.unwrap_or_default());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_claim_refund(
+    deps: DepsMut,
+    collection: String,
+    token_id: String,
+    recipient: Option<String>,
+) -> ContractResult {
+    let refund_recipient = load_claimable_refund(&deps, &collection, &token_id)?;
+    claim_refund(
+        deps,
+        collection,
+        token_id,
+        recipient.unwrap_or(refund_recipient),
+    )
+}
+
+fn sudo_force_unescrow(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    recipient: String,
+) -> ContractResult {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let in_flight_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+    let in_flight = IN_FLIGHT.may_load(deps.storage, in_flight_key)?;
+    // Acknowledgements only arrive on open channels. On an open channel, a packet that looks
+    // stuck may still be acknowledged as delivered, leaving both the name and its voucher out.
+    if !CLOSED_CHANNELS.has(deps.storage, &channel_id) {
+        return Err(ContractError::CantForceUnescrow { channel_id });
+    }
+    let fee = in_flight
+        .as_ref()
+        .and_then(|in_flight| in_flight.fee.to_owned());
+    let relayer_fee_refund = in_flight
+        .as_ref()
+        .and_then(|in_flight| in_flight.relayer_fee.to_owned())
+        .and_then(fees::refund_closed_relayer_fee);
+    let unescrow_transfer = |escrow_collection: String, escrow_token_id: String| {
+        to_json_binary(&CollectionExecuteMsg::TransferNft {
+            recipient: recipient.to_string(),
+            token_id: escrow_token_id,
+        })
+        .map(|msg| WasmMsg::Execute {
+            contract_addr: escrow_collection,
+            msg,
+            funds: vec![],
+        })
+    };
+    let (unescrow_exec_msg, escrowed) = match in_flight.map(|f| f.token) {
+        Some(EscrowedToken::Voucher) => (
+            unescrow_transfer(
+                VOUCHERS_ADDR.load(deps.storage)?,
+                load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?,
+            )?,
+            "voucher",
+        ),
+        Some(EscrowedToken::Teleported {
+            token_uri,
+            extension,
+        }) => (
+            remint_teleported_msg(
+                &collection,
+                &token_id,
+                recipient.as_str(),
+                token_uri,
+                extension,
+            )?,
+            "teleported",
+        ),
+        _ => {
+            if !ESCROWED_NAMES.has(deps.storage, in_flight_key) {
+                return Err(ContractError::NameNotEscrowed {
+                    collection,
+                    token_id,
+                });
+            }
+            remove_escrowed_name(deps.storage, in_flight_key)?;
+            (
+                unescrow_transfer(collection.to_owned(), token_id.to_owned())?,
+                "name",
+            )
+        }
+    };
+    IN_FLIGHT.remove(deps.storage, in_flight_key);
+    let fee_refund = fee
+        .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+        .transpose()?;
+    let force_unescrow_event = Event::new("my-ics-name-force-unescrow")
+        .add_attribute("channel", channel_id.to_owned())
+        .add_attribute("collection", collection.to_owned())
+        .add_attribute("token-id", token_id.to_owned())
+        .add_attribute("escrowed", escrowed)
+        .add_attribute("recipient", recipient)
+        .add_attribute("reason", "channel-closed");
+    Ok(Response::default()
+        .add_message(unescrow_exec_msg)
+        .add_submessages(fee_refund)
+        .add_messages(relayer_fee_refund)
+        .add_event(force_unescrow_event))
+}
+
+fn sudo_settle_channel(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    policy: SettlementPolicy,
+    limit: Option<u32>,
+) -> ContractResult {
+    if !CLOSED_CHANNELS.has(deps.storage, &channel_id) {
+        return Err(ContractError::ChannelNotClosed { channel_id });
+    }
+    let limit = limit.unwrap_or(DEFAULT_SETTLEMENT_LIMIT) as usize;
+    let in_flight_packets = IN_FLIGHT
+        .sub_prefix(&channel_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut refund_msgs = vec![];
+    let mut fee_refund_msgs = vec![];
+    let mut relayer_fee_refund_msgs = vec![];
+    for ((collection, token_id), in_flight) in in_flight_packets.iter() {
+        let in_flight_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+        IN_FLIGHT.remove(deps.storage, in_flight_key);
+        let refund_msg = match &in_flight.token {
+            EscrowedToken::Name => {
+                remove_escrowed_name(deps.storage, in_flight_key)?;
+                refund_submsg(
+                    deps.storage,
+                    collection.to_owned(),
+                    token_id.to_owned(),
+                    in_flight.refund_addr.to_owned(),
+                )?
+            }
+            EscrowedToken::Voucher => refund_submsg(
+                deps.storage,
+                VOUCHERS_ADDR.load(deps.storage)?,
+                load_voucher_token_id(deps.storage, &channel_id, collection, token_id)?,
+                in_flight.refund_addr.to_owned(),
+            )?,
+            EscrowedToken::Teleported {
+                token_uri,
+                extension,
+            } => remint_submsg(
+                deps.storage,
+                collection.to_owned(),
+                token_id.to_owned(),
+                in_flight.refund_addr.to_owned(),
+                Remint {
+                    token_uri: token_uri.to_owned(),
+                    extension: extension.to_owned(),
+                },
+            )?,
+        };
+        refund_msgs.push(refund_msg);
+        fee_refund_msgs.extend(
+            in_flight
+                .fee
+                .to_owned()
+                .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+                .transpose()?,
+        );
+        relayer_fee_refund_msgs.extend(
+            in_flight
+                .relayer_fee
+                .to_owned()
+                .and_then(fees::refund_closed_relayer_fee),
+        );
+    }
+    let mut reclaimable_count = 0;
+    let mut burn_msgs = vec![];
+    if policy == SettlementPolicy::ReclaimEscrowed {
+        let escrows = ESCROWED_NAMES
+            .sub_prefix(&channel_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        for ((collection, token_id), escrow) in escrows.iter() {
+            remove_escrowed_name(deps.storage, (&channel_id, collection, token_id))?;
+            // Names registered from the other chain have no sender to reclaim them.
+            if escrow.sender_addr == env.contract.address {
+                burn_msgs.push(WasmMsg::Execute {
+                    contract_addr: collection.to_owned(),
+                    msg: to_json_binary(&CollectionExecuteMsg::Burn {
+                        token_id: token_id.to_owned(),
+                    })?,
+                    funds: vec![],
+                });
+                continue;
             }
+            CLAIMABLE_REFUNDS.save(deps.storage, (collection, token_id), &escrow.sender_addr)?;
+            reclaimable_count += 1;
         }
+    }
+    let settle_event = Event::new("my-ics-name-channel-settle")
+        .add_attribute("channel", channel_id)
+        .add_attribute("refunded", refund_msgs.len().to_string())
+        .add_attribute("reclaimable", reclaimable_count.to_string());
+    Ok(Response::default()
+        .add_submessages(refund_msgs)
+        .add_submessages(fee_refund_msgs)
+        .add_messages(relayer_fee_refund_msgs)
+        .add_messages(burn_msgs)
+        .add_event(settle_event))
+}
+
+fn sudo_close_channel(deps: DepsMut, env: Env, channel_id: String) -> ContractResult {
+    validate_channel_open(&deps, &channel_id)?;
+    let has_in_flight = IN_FLIGHT
+        .sub_prefix(&channel_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if has_in_flight {
+        return Err(ContractError::ChannelHasInFlightPackets { channel_id });
+    }
+    CLOSE_REQUESTS.save(deps.storage, &channel_id, &env.block.time)?;
+    let close_event = Event::new("my-ics-name-channel-close").add_attribute("channel", &channel_id);
+    Ok(Response::default()
+        .add_message(IbcMsg::CloseChannel { channel_id })
+        .add_event(close_event))
+}
+
+fn sudo_update_trusted_counterparty(
+    deps: DepsMut,
+    connection_id: String,
+    port_id: String,
+    trusted: bool,
+) -> ContractResult {
+    if trusted {
+        TRUSTED_COUNTERPARTIES.save(deps.storage, (&connection_id, &port_id), &Empty {})?;
+    } else {
+        TRUSTED_COUNTERPARTIES.remove(deps.storage, (&connection_id, &port_id));
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-trusted-counterparty", connection_id)
+        .add_attribute("port", port_id)
+        .add_attribute("trusted", trusted.to_string());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_any_counterparty_allowed(deps: DepsMut, allowed: bool) -> ContractResult {
+    ANY_COUNTERPARTY_ALLOWED.save(deps.storage, &allowed)?;
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-any-counterparty-allowed", allowed.to_string());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_one_channel_per_counterparty(deps: DepsMut, one_channel: bool) -> ContractResult {
+    ONE_CHANNEL_PER_COUNTERPARTY.save(deps.storage, &one_channel)?;
+    let sudo_event = Event::new("my-ics-name").add_attribute(
+        "update-one-channel-per-counterparty",
+        one_channel.to_string(),
+    );
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_flat_fee(deps: DepsMut, fee: Option<Coin>) -> ContractResult {
+    match &fee {
+        Some(fee) => FLAT_FEE.save(deps.storage, fee)?,
+        None => FLAT_FEE.remove(deps.storage),
+    }
+    let sudo_event = Event::new("my-ics-name").add_attribute(
+        "update-flat-fee",
+        fee.map(|fee| fee.to_string()).unwrap_or_default(),
+    );
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_collection_fee(
+    deps: DepsMut,
+    collection: String,
+    fee: Option<Coin>,
+) -> ContractResult {
+    match &fee {
+        Some(fee) => COLLECTION_FEES.save(deps.storage, &collection, fee)?,
+        None => COLLECTION_FEES.remove(deps.storage, &collection),
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-collection-fee", collection)
+        .add_attribute("fee", fee.map(|fee| fee.to_string()).unwrap_or_default());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_cw20_fee(deps: DepsMut, cw20_addr: String, fee: Option<Uint128>) -> ContractResult {
+    let cw20_addr = deps.api.addr_validate(&cw20_addr)?;
+    match fee {
+        Some(fee) => CW20_FEES.save(deps.storage, cw20_addr.as_str(), &fee)?,
+        None => CW20_FEES.remove(deps.storage, cw20_addr.as_str()),
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-cw20-fee", cw20_addr)
+        .add_attribute("fee", fee.map(|fee| fee.to_string()).unwrap_or_default());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_treasury(deps: DepsMut, treasury: String) -> ContractResult {
+    let treasury = deps.api.addr_validate(&treasury)?;
+    TREASURY.save(deps.storage, &treasury.to_string())?;
+    let sudo_event = Event::new("my-ics-name").add_attribute("update-treasury", treasury);
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_registrable_collection(
+    deps: DepsMut,
+    collection: String,
+    registrable: bool,
+) -> ContractResult {
+    if registrable {
+        REGISTRABLE_COLLECTIONS.save(deps.storage, &collection, &Empty {})?;
+    } else {
+        REGISTRABLE_COLLECTIONS.remove(deps.storage, &collection);
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-registrable-collection", collection)
+        .add_attribute("registrable", registrable.to_string());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_renewal_config(
+    deps: DepsMut,
+    collection: String,
+    config: Option<RenewalConfig>,
+) -> ContractResult {
+    match &config {
+        Some(config) => RENEWAL_CONFIGS.save(deps.storage, &collection, config)?,
+        None => RENEWAL_CONFIGS.remove(deps.storage, &collection),
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-renewal-config", collection)
+        .add_attribute(
+            "price",
+            config
+                .map(|config| config.price.to_string())
+                .unwrap_or_default(),
+        );
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_expiry_policy(
+    deps: DepsMut,
+    collection: String,
+    policy: Option<ExpiryPolicy>,
+) -> ContractResult {
+    match &policy {
+        Some(policy) => EXPIRY_POLICIES.save(deps.storage, &collection, policy)?,
+        None => EXPIRY_POLICIES.remove(deps.storage, &collection),
+    }
+    let policy = match policy {
+        Some(ExpiryPolicy::Burn) => "burn",
+        Some(ExpiryPolicy::ReturnToSender) => "return-to-sender",
+        None => "",
+    };
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-expiry-policy", collection)
+        .add_attribute("policy", policy);
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_voucher_id_scheme(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    scheme: Option<VoucherIdScheme>,
+) -> ContractResult {
+    let key = (channel_id.as_str(), collection.as_str());
+    match &scheme {
+        Some(scheme) => VOUCHER_ID_SCHEMES.save(deps.storage, key, scheme)?,
+        None => VOUCHER_ID_SCHEMES.remove(deps.storage, key),
+    }
+    let scheme = match scheme {
+        Some(VoucherIdScheme::Hash) => "hash",
+        Some(VoucherIdScheme::TokenId) => "token-id",
+        Some(VoucherIdScheme::ChannelTokenId) => "channel-token-id",
+        None => "",
+    };
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-voucher-id-scheme", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("scheme", scheme);
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn sudo_update_teleport_collection(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    remote_collection: Option<String>,
+) -> ContractResult {
+    let key = (channel_id.as_str(), collection.as_str());
+    if let Some(previous) = TELEPORT_COLLECTIONS.may_load(deps.storage, key)? {
+        TELEPORT_REMOTES.remove(deps.storage, (&channel_id, &previous));
+    }
+    match &remote_collection {
+        Some(remote_collection) => {
+            TELEPORT_COLLECTIONS.save(deps.storage, key, remote_collection)?;
+            TELEPORT_REMOTES.save(deps.storage, (&channel_id, remote_collection), &collection)?;
+        }
+        None => TELEPORT_COLLECTIONS.remove(deps.storage, key),
+    }
+    let sudo_event = Event::new("my-ics-name")
+        .add_attribute("update-teleport-collection", collection)
+        .add_attribute("channel", channel_id)
+        .add_attribute("remote-collection", remote_collection.unwrap_or_default());
+    Ok(Response::default().add_event(sudo_event))
+}
+
+fn append_vouchers_attributes(my_event: Event, vouchers_addr: &Option<String>) -> Event {
+    if let Some(addr) = vouchers_addr {
+        my_event.add_attribute("update-vouchers-address", addr)
+    } else {
+        my_event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use cosmwasm_std::{
+        from_json,
+        testing::{self, MockApi, MockQuerier, MockStorage},
+        to_json_binary, Addr, BankMsg, Coin, ContractResult, Empty, Event, IbcMsg, IbcTimeout,
+        OwnedDeps, Querier, QuerierResult, QueryRequest, Reply, Response, SubMsg, SubMsgResult,
+        SystemError, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use cw20::Cw20ReceiveMsg;
+    use cw721::msg::{NftInfoResponse, OwnerOfResponse};
+
+    use crate::{
+        error::ContractError,
+        ibc::{helpers::compute_voucher_token_id, ics29::pay_packet_fee_msg},
+        msg::{
+            ChannelConfig, CollectionExecuteMsg, CollectionQueryMsg, Cw20HookMsg, ExecuteMsg,
+            IbcPacketMessage, InstantiateMsg, JsonValue, LegacyVoucher, MetadataExecuteMsg,
+            MigrateMsg, NameserviceQueryMsg, QueryMsg, RelayerFee, RenewalConfig, RenewalPayment,
+            SettlementPolicy, SudoMsg, SupplyResponse, TransferMode,
+        },
+        state::{
+            Escrow, EscrowedToken, FeeAmount, InFlight, PaidFee, PaidRelayerFee, PendingClaim,
+            Refund, Remint, CHANNEL_CONFIGS, CLAIMABLE_REFUNDS, CLAIMABLE_REMINTS, CLOSED_CHANNELS,
+            CLOSE_REQUESTS, ESCROWED_NAMES, IN_FLIGHT, PENDING_CLAIMS, PENDING_FEE_REFUNDS,
+            PENDING_REFUNDS, RENEWAL_CONFIGS, RENEWAL_DEPOSITS, VOUCHERS_ADDR, VOUCHER_IDS,
+            VOUCHER_SUPPLY, VOUCHER_TRACES,
+        },
+    };
+
+    pub fn mock_deps(
+        response: OwnerOfResponse,
+    ) -> OwnedDeps<MockStorage, MockApi, OwnerOfMockQuerier, Empty> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: OwnerOfMockQuerier::new(MockQuerier::new(&[]), response),
+            custom_query_type: PhantomData,
+        }
+    }
+
+    pub struct OwnerOfMockQuerier {
+        base: MockQuerier,
+        response: OwnerOfResponse,
+    }
+
+    impl Querier for OwnerOfMockQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = match from_json(bin_request) {
+                Ok(v) => v,
+                Err(e) => {
+                    return SystemResult::Err(SystemError::InvalidRequest {
+                        error: format!("Parsing query request: {}", e),
+                        request: bin_request.into(),
+                    })
+                }
+            };
+
+            self.handle_query(&request)
+        }
+    }
+
+    impl OwnerOfMockQuerier {
+        pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: _,
+                    msg,
+                }) if from_json::<NameserviceQueryMsg>(msg).is_ok() => {
+                    SystemResult::Ok(ContractResult::Err("Unknown query".to_owned()))
+                }
+                QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: _,
+                    msg,
+                }) => {
+                    let expected = to_json_binary(&CollectionQueryMsg::OwnerOf {
+                        token_id: "3".to_owned(),
+                        include_expired: None,
+                    })
+                    .expect("Failed to create expected query");
+                    assert_eq!(expected.to_vec(), msg.to_vec(), "Query is not owner");
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&self.response).expect("Failed to serialize owner response"),
+                    ))
+                }
+                _ => self.base.handle_query(request),
+            }
+        }
+
+        pub fn new(base: MockQuerier<Empty>, response: OwnerOfResponse) -> Self {
+            OwnerOfMockQuerier { base, response }
+        }
+    }
+
+    #[test]
+    fn test_instantiate() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let vouchers = Addr::unchecked("vouchers");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some(vouchers.to_string()),
+        };
+
+        // Act
+        let result = super::instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to instantiate ics name");
+        let received_response = result.unwrap();
+        let expected_response = Response::default().add_event(
+            Event::new("my-ics-name")
+                .add_attribute("update-vouchers-address", vouchers.to_string()),
+        );
+        assert_eq!(received_response, expected_response);
+        let saved_vouchers = VOUCHERS_ADDR
+            .load(&mocked_deps_mut.storage)
+            .expect("Failed to load vouchers address");
+        assert_eq!(saved_vouchers, vouchers.to_string());
+    }
+
+    #[test]
+    fn test_execute_transfer_name() {
+        // Arrange
+        let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
+            owner: "sender".to_owned(),
+            approvals: vec![],
+        });
+        let mocked_env = testing::mock_env();
+        let sender = Addr::unchecked("sender");
+        let mocked_msg_info = testing::mock_info(sender.as_ref(), &[]);
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            transfer_msg.to_owned(),
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to execute name transfer");
+        let received_response = result.unwrap();
+        let expected_escrow_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: mocked_env.contract.address.to_string(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        let expected_transfer_packet = IbcMsg::SendPacket {
+            channel_id: "2".to_owned(),
+            data: to_json_binary(&IbcPacketMessage::TransferName {
+                collection: "original".to_owned(),
+                token_id: "3".to_owned(),
+                sender_addr: "sender".to_owned(),
+                receiver_addr: "receiver".to_owned(),
+                refund_addr: None,
+                expires_at: None,
+                mode: TransferMode::Escrow,
+            })
+            .expect("Failed to serialize transfer message"),
+            timeout: IbcTimeout::with_timestamp(mocked_env.block.time.plus_seconds(120)),
+        };
+        let expected_response = Response::default()
+            .add_message(expected_escrow_exec_msg)
+            .add_message(expected_transfer_packet);
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_execute_transfer_name_wrong_receiver_prefix() {
+        // Arrange
+        let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
+            owner: "sender".to_owned(),
+            approvals: vec![],
+        });
+        let mocked_env = testing::mock_env();
+        let sender = Addr::unchecked("sender");
+        let mocked_msg_info = testing::mock_info(sender.as_ref(), &[]);
+        CHANNEL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "2",
+                &ChannelConfig {
+                    bech32_prefix: Some("other".to_owned()),
+                    ..ChannelConfig::default()
+                },
+            )
+            .expect("Failed to save channel config");
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            transfer_msg.to_owned(),
+        );
+
+        // Assert
+        assert!(
+            matches!(
+                result,
+                Err(ContractError::InvalidReceiverPrefix { receiver_addr, expected })
+                    if receiver_addr == "receiver" && expected == "other"
+            ),
+            "Should have rejected receiver prefix"
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_name_by_approved_spender() {
+        // Arrange
+        let owner_of_response = from_json::<OwnerOfResponse>(
+            br#"{"owner":"owner","approvals":[{"spender":"sender","expires":{"never":{}}}]}"#,
+        )
+        .expect("Failed to deserialize owner response");
+        let mut mocked_deps_mut = mock_deps(owner_of_response);
+        let mocked_env = testing::mock_env();
+        let sender = Addr::unchecked("sender");
+        let mocked_msg_info = testing::mock_info(sender.as_ref(), &[]);
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            transfer_msg.to_owned(),
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to execute name transfer");
+        let received_response = result.unwrap();
+        let expected_transfer_packet = IbcMsg::SendPacket {
+            channel_id: "2".to_owned(),
+            data: to_json_binary(&IbcPacketMessage::TransferName {
+                collection: "original".to_owned(),
+                token_id: "3".to_owned(),
+                sender_addr: "owner".to_owned(),
+                receiver_addr: "receiver".to_owned(),
+                refund_addr: None,
+                expires_at: None,
+                mode: TransferMode::Escrow,
+            })
+            .expect("Failed to serialize transfer message"),
+            timeout: IbcTimeout::with_timestamp(mocked_env.block.time.plus_seconds(120)),
+        };
+        assert_eq!(received_response.messages.len(), 2);
+        assert_eq!(
+            received_response.messages[1].msg,
+            expected_transfer_packet.into()
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_name_by_stranger() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        mocked_deps_mut.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => match from_json::<CollectionQueryMsg>(msg) {
+                Ok(CollectionQueryMsg::OwnerOf { .. }) => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&OwnerOfResponse {
+                        owner: "owner".to_owned(),
+                        approvals: vec![],
+                    })
+                    .expect("Failed to serialize owner response"),
+                )),
+                _ => SystemResult::Ok(ContractResult::Err(
+                    "Approval not found not found".to_owned(),
+                )),
+            },
+            _ => panic!("Unexpected query"),
+        });
+        let mocked_env = testing::mock_env();
+        let mocked_msg_info = testing::mock_info("stranger", &[]);
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            transfer_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::OnlyOwner)),
+            "Should refuse a sender who is neither owner nor operator"
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_name_operator_query_fails() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        mocked_deps_mut.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => match from_json::<CollectionQueryMsg>(msg) {
+                Ok(CollectionQueryMsg::OwnerOf { .. }) => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&OwnerOfResponse {
+                        owner: "owner".to_owned(),
+                        approvals: vec![],
+                    })
+                    .expect("Failed to serialize owner response"),
+                )),
+                _ => SystemResult::Ok(ContractResult::Err("Unknown query".to_owned())),
+            },
+            _ => panic!("Unexpected query"),
+        });
+        let mocked_env = testing::mock_env();
+        let mocked_msg_info = testing::mock_info("stranger", &[]);
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            transfer_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::Std(_))),
+            "Should surface collection errors other than a missing operator"
+        );
+    }
+
+    #[test]
+    fn test_sync_metadata_by_anyone() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        mocked_deps_mut.querier.update_wasm(|_| {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&NftInfoResponse {
+                    token_uri: Some("https://example.com/3".to_owned()),
+                    extension: Some(JsonValue::String("three".to_owned())),
+                })
+                .expect("Failed to serialize nft info response"),
+            ))
+        });
+        let sync_msg = ExecuteMsg::SyncMetadata {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("anyone", &[]),
+            sync_msg,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to sync metadata");
+        let received_response = result.unwrap();
+        let expected_sync_msg = IbcPacketMessage::SyncMetadata {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            token_uri: Some("https://example.com/3".to_owned()),
+            extension: Some(JsonValue::String("three".to_owned())),
+        };
+        let expected_sync_packet = IbcMsg::SendPacket {
+            channel_id: "2".to_owned(),
+            data: to_json_binary(&expected_sync_msg).expect("Failed to serialize sync msg"),
+            timeout: IbcTimeout::with_timestamp(mocked_env.block.time.plus_seconds(120)),
+        };
+        assert_eq!(
+            received_response.messages,
+            vec![SubMsg::new(expected_sync_packet)]
+        );
+    }
+
+    #[test]
+    fn test_withdraw_renewal_deposit() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        RENEWAL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "original",
+                &RenewalConfig {
+                    price: Coin::new(60, "uname"),
+                    payment: RenewalPayment::Deposit,
+                },
+            )
+            .expect("Failed to save renewal config");
+        let deposit_msg = ExecuteMsg::DepositRenewal {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let other_deposit = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("someone", &[Coin::new(1, "uname")]),
+            deposit_msg.to_owned(),
+        );
+        super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("depositor", &[Coin::new(100, "uname")]),
+            deposit_msg,
+        )
+        .expect("Failed to deposit");
+        RENEWAL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "original",
+                &RenewalConfig {
+                    price: Coin::new(60, "uother"),
+                    payment: RenewalPayment::Deposit,
+                },
+            )
+            .expect("Failed to save renewal config");
+        let withdraw_msg = ExecuteMsg::WithdrawRenewalDeposit {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let no_deposit_withdraw = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("nobody", &[]),
+            withdraw_msg.to_owned(),
+        );
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env,
+            testing::mock_info("depositor", &[]),
+            withdraw_msg,
+        );
+
+        // Assert
+        assert!(
+            other_deposit.is_ok(),
+            "Failed to deposit alongside another depositor"
+        );
+        assert!(
+            matches!(
+                no_deposit_withdraw,
+                Err(ContractError::NoRenewalDeposit { .. })
+            ),
+            "Should not withdraw without a deposit"
+        );
+        assert!(result.is_ok(), "Failed to withdraw renewal deposit");
+        let received_response = result.unwrap();
+        let expected_withdraw_msg = BankMsg::Send {
+            to_address: "depositor".to_owned(),
+            amount: vec![Coin::new(100, "uname")],
+        };
+        assert_eq!(received_response.messages.len(), 1);
+        assert_eq!(
+            received_response.messages[0].msg,
+            expected_withdraw_msg.into()
+        );
+        assert!(!RENEWAL_DEPOSITS.has(&mocked_deps_mut.storage, ("original", "3", "depositor")));
+        assert!(RENEWAL_DEPOSITS.has(&mocked_deps_mut.storage, ("original", "3", "someone")));
+    }
+
+    #[test]
+    fn test_failed_refund_becomes_claimable() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        PENDING_REFUNDS
+            .push_back(
+                &mut mocked_deps_mut.storage,
+                &Refund {
+                    contract_addr: "original".to_owned(),
+                    token_id: "3".to_owned(),
+                    recipient: "sender".to_owned(),
+                    remint: None,
+                },
+            )
+            .expect("Failed to save pending refund");
+        let failed_reply = Reply {
+            id: super::REFUND_REPLY_ID,
+            result: SubMsgResult::Err("collection paused".to_owned()),
+        };
+        let claim_msg = ExecuteMsg::ClaimRefund {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            recipient: None,
+        };
+
+        // Act
+        let reply_result = super::reply(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            failed_reply,
+        );
+        let claim_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[]),
+            claim_msg,
+        );
+
+        // Assert
+        assert!(reply_result.is_ok(), "Failed to handle refund reply");
+        assert!(claim_result.is_ok(), "Failed to claim refund");
+        let received_response = claim_result.unwrap();
+        let expected_claim_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: "sender".to_owned(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        assert_eq!(received_response.messages.len(), 1);
+        assert_eq!(
+            received_response.messages[0].msg,
+            expected_claim_exec_msg.into()
+        );
+        let claimable = CLAIMABLE_REFUNDS
+            .may_load(&mocked_deps_mut.storage, ("original", "3"))
+            .expect("Failed to load claimable refund");
+        assert_eq!(claimable, None);
+    }
+
+    #[test]
+    fn test_failed_remint_becomes_claimable() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let remint = Remint {
+            token_uri: Some("https://example.com/3".to_owned()),
+            extension: Some(JsonValue::String("three".to_owned())),
+        };
+        PENDING_REFUNDS
+            .push_back(
+                &mut mocked_deps_mut.storage,
+                &Refund {
+                    contract_addr: "original".to_owned(),
+                    token_id: "3".to_owned(),
+                    recipient: "sender".to_owned(),
+                    remint: Some(remint.to_owned()),
+                },
+            )
+            .expect("Failed to save pending refund");
+        let failed_reply = Reply {
+            id: super::REFUND_REPLY_ID,
+            result: SubMsgResult::Err("collection paused".to_owned()),
+        };
+        let claim_msg = ExecuteMsg::ClaimRefund {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            recipient: None,
+        };
+
+        // Act
+        let reply_result = super::reply(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            failed_reply,
+        );
+        let claim_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[]),
+            claim_msg,
+        );
+
+        // Assert
+        assert!(reply_result.is_ok(), "Failed to handle refund reply");
+        assert!(claim_result.is_ok(), "Failed to claim refund");
+        let received_response = claim_result.unwrap();
+        let expected_claim_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&MetadataExecuteMsg::Mint {
+                token_id: "3".to_owned(),
+                owner: "sender".to_owned(),
+                token_uri: remint.token_uri,
+                extension: remint.extension,
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        assert_eq!(received_response.messages.len(), 1);
+        assert_eq!(
+            received_response.messages[0].msg,
+            expected_claim_exec_msg.into()
+        );
+        assert!(!CLAIMABLE_REMINTS.has(&mocked_deps_mut.storage, ("original", "3")));
+    }
+
+    #[test]
+    fn test_failed_fee_refund_becomes_claimable() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        PENDING_FEE_REFUNDS
+            .push_back(
+                &mut mocked_deps_mut.storage,
+                &PaidFee {
+                    payer: "sender".to_owned(),
+                    amount: FeeAmount::Native(Coin::new(100, "uname")),
+                },
+            )
+            .expect("Failed to save pending fee refund");
+        let failed_reply = Reply {
+            id: super::FEE_REFUND_REPLY_ID,
+            result: SubMsgResult::Err("blocked address".to_owned()),
+        };
+        let claim_msg = ExecuteMsg::ClaimFees {
+            recipient: Some("other".to_owned()),
+        };
+
+        // Act
+        let reply_result = super::reply(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            failed_reply,
+        );
+        let claim_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[]),
+            claim_msg.to_owned(),
+        );
+        let claim_again_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env,
+            testing::mock_info("sender", &[]),
+            claim_msg,
+        );
+
+        // Assert
+        assert!(reply_result.is_ok(), "Failed to handle fee refund reply");
+        assert!(claim_result.is_ok(), "Failed to claim fees");
+        let received_response = claim_result.unwrap();
+        let expected_claim_msg = BankMsg::Send {
+            to_address: "other".to_owned(),
+            amount: vec![Coin::new(100, "uname")],
+        };
+        assert_eq!(received_response.messages.len(), 1);
+        assert_eq!(received_response.messages[0].msg, expected_claim_msg.into());
+        assert!(
+            matches!(claim_again_result, Err(ContractError::NoClaimableFees)),
+            "Should not claim fees twice"
+        );
+    }
+
+    #[test]
+    fn test_force_unescrow_on_closed_channel() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        let force_unescrow_msg = SudoMsg::ForceUnescrow {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            recipient: "rescuer".to_owned(),
+        };
+        let refused = super::sudo(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            force_unescrow_msg.to_owned(),
+        );
+        CLOSED_CHANNELS
+            .save(&mut mocked_deps_mut.storage, "2", &mocked_env.block.time)
+            .expect("Failed to close channel");
+
+        // Act
+        let result = super::sudo(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            force_unescrow_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(refused, Err(ContractError::CantForceUnescrow { .. })),
+            "Should not force unescrow on an open channel"
+        );
+        assert!(result.is_ok(), "Failed to force unescrow");
+        let received_response = result.unwrap();
+        let expected_unescrow_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: "rescuer".to_owned(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-force-unescrow")
+            .add_attribute("channel", "2")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3")
+            .add_attribute("escrowed", "name")
+            .add_attribute("recipient", "rescuer")
+            .add_attribute("reason", "channel-closed");
+        let expected_response = Response::default()
+            .add_message(expected_unescrow_exec_msg)
+            .add_event(expected_event);
+        assert_eq!(received_response, expected_response);
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("2", "original", "3")));
+    }
 
-        pub fn new(base: MockQuerier<Empty>, response: OwnerOfResponse) -> Self {
-            OwnerOfMockQuerier { base, response }
+    #[test]
+    fn test_migrate_traces_legacy_vouchers() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let migrate_msg = MigrateMsg {
+            escrowed_names: vec![],
+            legacy_in_flight: false,
+            vouchers: vec![LegacyVoucher {
+                channel_id: "2".to_owned(),
+                collection: "remote".to_owned(),
+                token_id: "3".to_owned(),
+            }],
+        };
+
+        // Act
+        let result = super::migrate(mocked_deps_mut.as_mut(), mocked_env, migrate_msg);
+
+        // Assert
+        assert!(result.is_ok(), "Failed to migrate");
+        let voucher_token_id = compute_voucher_token_id("2", "remote", "3");
+        let trace = VOUCHER_TRACES
+            .load(&mocked_deps_mut.storage, &voucher_token_id)
+            .expect("Failed to load voucher trace");
+        assert_eq!(trace.token_id, "3");
+        let supply = VOUCHER_SUPPLY
+            .load(&mocked_deps_mut.storage, ("2", "remote"))
+            .expect("Failed to load voucher supply");
+        assert_eq!(supply, 1);
+    }
+
+    #[test]
+    fn test_settle_closed_channel() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        for token_id in ["3", "4"] {
+            ESCROWED_NAMES
+                .save(
+                    &mut mocked_deps_mut.storage,
+                    ("2", "original", token_id),
+                    &Escrow {
+                        sender_addr: "sender".to_owned(),
+                    },
+                )
+                .expect("Failed to save escrow");
         }
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "refund".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: None,
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        CLOSED_CHANNELS
+            .save(&mut mocked_deps_mut.storage, "2", &mocked_env.block.time)
+            .expect("Failed to close channel");
+        let settle_msg = SudoMsg::SettleChannel {
+            channel_id: "2".to_owned(),
+            policy: SettlementPolicy::ReclaimEscrowed,
+            limit: None,
+        };
+
+        // Act
+        let result = super::sudo(mocked_deps_mut.as_mut(), mocked_env.to_owned(), settle_msg);
+
+        // Assert
+        assert!(result.is_ok(), "Failed to settle channel");
+        let received_response = result.unwrap();
+        let expected_refund_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: "refund".to_owned(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-channel-settle")
+            .add_attribute("channel", "2")
+            .add_attribute("refunded", "1")
+            .add_attribute("reclaimable", "1");
+        let expected_response = Response::default()
+            .add_submessage(SubMsg::reply_always(
+                expected_refund_exec_msg,
+                super::REFUND_REPLY_ID,
+            ))
+            .add_event(expected_event);
+        assert_eq!(received_response, expected_response);
+        let claimable = CLAIMABLE_REFUNDS
+            .may_load(&mocked_deps_mut.storage, ("original", "4"))
+            .expect("Failed to load claimable refund");
+        assert_eq!(claimable, Some("sender".to_owned()));
+        assert!(!IN_FLIGHT.has(&mocked_deps_mut.storage, ("2", "original", "3")));
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("2", "original", "3")));
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("2", "original", "4")));
     }
 
     #[test]
-    fn test_instantiate() {
+    fn test_force_unescrow_refunds_relayer_fee() {
         // Arrange
         let mut mocked_deps_mut = testing::mock_dependencies();
         let mocked_env = testing::mock_env();
-        let deployer = Addr::unchecked("deployer");
-        let vouchers = Addr::unchecked("vouchers");
-        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
-        let instantiate_msg = InstantiateMsg {
-            vouchers_addr: Some(vouchers.to_string()),
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "sender".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: Some(PaidRelayerFee {
+                        payer: "sender".to_owned(),
+                        fee: RelayerFee {
+                            recv_fee: vec![Coin::new(10, "uname")],
+                            ack_fee: vec![Coin::new(5, "uname")],
+                            timeout_fee: vec![Coin::new(7, "uname")],
+                        },
+                    }),
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        CLOSED_CHANNELS
+            .save(&mut mocked_deps_mut.storage, "2", &mocked_env.block.time)
+            .expect("Failed to close channel");
+        let force_unescrow_msg = SudoMsg::ForceUnescrow {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            recipient: "rescuer".to_owned(),
         };
 
         // Act
-        let result = super::instantiate(
+        let result = super::sudo(mocked_deps_mut.as_mut(), mocked_env, force_unescrow_msg);
+
+        // Assert
+        assert!(result.is_ok(), "Failed to force unescrow");
+        let received_response = result.unwrap();
+        let expected_refund_msg = BankMsg::Send {
+            to_address: "sender".to_owned(),
+            amount: vec![Coin::new(22, "uname")],
+        };
+        assert_eq!(received_response.messages.len(), 2);
+        assert_eq!(
+            received_response.messages[1].msg,
+            expected_refund_msg.into()
+        );
+    }
+
+    #[test]
+    fn test_close_channel_without_in_flight_packets() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "sender".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: None,
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        let close_msg = SudoMsg::CloseChannel {
+            channel_id: "2".to_owned(),
+        };
+        let refused = super::sudo(
             mocked_deps_mut.as_mut(),
             mocked_env.to_owned(),
-            mocked_msg_info,
-            instantiate_msg.to_owned(),
+            close_msg.to_owned(),
         );
+        IN_FLIGHT.remove(&mut mocked_deps_mut.storage, ("2", "original", "3"));
+
+        // Act
+        let result = super::sudo(mocked_deps_mut.as_mut(), mocked_env.to_owned(), close_msg);
 
         // Assert
-        assert!(result.is_ok(), "Failed to instantiate ics name");
-        let received_response = result.unwrap();
-        let expected_response = Response::default().add_event(
-            Event::new("my-ics-name")
-                .add_attribute("update-vouchers-address", vouchers.to_string()),
+        assert!(
+            matches!(
+                refused,
+                Err(ContractError::ChannelHasInFlightPackets { .. })
+            ),
+            "Should not close a channel with packets in flight"
         );
+        assert!(result.is_ok(), "Failed to close channel");
+        let received_response = result.unwrap();
+        let expected_response = Response::default()
+            .add_message(IbcMsg::CloseChannel {
+                channel_id: "2".to_owned(),
+            })
+            .add_event(Event::new("my-ics-name-channel-close").add_attribute("channel", "2"));
         assert_eq!(received_response, expected_response);
-        let saved_vouchers = VOUCHERS_ADDR
-            .load(&mocked_deps_mut.storage)
-            .expect("Failed to load vouchers address");
-        assert_eq!(saved_vouchers, vouchers.to_string());
+        assert!(CLOSE_REQUESTS.has(&mocked_deps_mut.storage, "2"));
     }
 
     #[test]
-    fn test_execute_transfer_name() {
+    fn test_execute_transfer_name_supply_cap() {
         // Arrange
         let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
             owner: "sender".to_owned(),
             approvals: vec![],
         });
         let mocked_env = testing::mock_env();
-        let sender = Addr::unchecked("sender");
-        let mocked_msg_info = testing::mock_info(sender.as_ref(), &[]);
+        let mocked_msg_info = testing::mock_info("sender", &[]);
+        CHANNEL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "2",
+                &ChannelConfig {
+                    supply_cap: Some(1),
+                    ..ChannelConfig::default()
+                },
+            )
+            .expect("Failed to save channel config");
         let transfer_msg = ExecuteMsg::IbcTransferName {
             channel_id: "2".to_owned(),
             collection: "original".to_owned(),
             token_id: "3".to_owned(),
             receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
         };
 
         // Act
-        let result = super::execute(
+        let first_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info.to_owned(),
+            transfer_msg.to_owned(),
+        );
+        let second_result = super::execute(
             mocked_deps_mut.as_mut(),
             mocked_env.to_owned(),
             mocked_msg_info,
+            transfer_msg,
+        );
+
+        // Assert
+        assert!(first_result.is_ok(), "Failed to execute name transfer");
+        assert!(
+            matches!(
+                second_result,
+                Err(ContractError::SupplyCapReached { supply_cap: 1, .. })
+            ),
+            "Should have rejected transfer beyond supply cap"
+        );
+        let supply = from_json::<SupplyResponse>(
+            super::query(
+                mocked_deps_mut.as_ref(),
+                mocked_env,
+                QueryMsg::Supply {
+                    channel_id: "2".to_owned(),
+                    collection: "original".to_owned(),
+                },
+            )
+            .expect("Failed to query supply"),
+        )
+        .expect("Failed to deserialize supply");
+        assert_eq!(
+            supply,
+            SupplyResponse {
+                escrowed: 1,
+                vouchers: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_name_collection_fee() {
+        // Arrange
+        let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
+            owner: "sender".to_owned(),
+            approvals: vec![],
+        });
+        let mocked_env = testing::mock_env();
+        let _ = super::sudo(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            SudoMsg::UpdateFlatFee(Some(Coin::new(10, "uname"))),
+        )
+        .expect("Failed to update flat fee");
+        let _ = super::sudo(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            SudoMsg::UpdateCollectionFee {
+                collection: "original".to_owned(),
+                fee: Some(Coin::new(100, "uname")),
+            },
+        )
+        .expect("Failed to update collection fee");
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: None,
+        };
+
+        // Act
+        let flat_fee_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[Coin::new(10, "uname")]),
             transfer_msg.to_owned(),
         );
+        let collection_fee_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[Coin::new(100, "uname")]),
+            transfer_msg,
+        );
 
         // Assert
-        assert!(result.is_ok(), "Failed to execute name transfer");
-        let received_response = result.unwrap();
-        let expected_escrow_exec_msg = WasmMsg::Execute {
-            contract_addr: "original".to_owned(),
-            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
-                recipient: mocked_env.contract.address.to_string(),
-                token_id: "3".to_owned(),
+        assert!(
+            matches!(
+                flat_fee_result,
+                Err(ContractError::IncorrectFee { expected, paid })
+                    if expected == "100uname" && paid == "10uname"
+            ),
+            "Should have rejected the flat fee"
+        );
+        assert!(
+            collection_fee_result.is_ok(),
+            "Failed to execute name transfer"
+        );
+        let in_flight = IN_FLIGHT
+            .load(&mocked_deps_mut.storage, ("2", "original", "3"))
+            .expect("Failed to load in-flight packet");
+        assert_eq!(
+            in_flight.fee,
+            Some(PaidFee {
+                payer: "sender".to_owned(),
+                amount: FeeAmount::Native(Coin::new(100, "uname")),
             })
-            .expect("Failed to serialize collection message"),
-            funds: vec![],
-        };
-        let expected_transfer_packet = IbcMsg::SendPacket {
-            channel_id: "2".to_owned(),
-            data: to_json_binary(&IbcPacketMessage::TransferName {
+        );
+    }
+
+    #[test]
+    fn test_receive_cw20_transfer_name() {
+        // Arrange
+        let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
+            owner: "sender".to_owned(),
+            approvals: vec![],
+        });
+        let mocked_env = testing::mock_env();
+        let _ = super::sudo(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            SudoMsg::UpdateCw20Fee {
+                cw20_addr: "allowed".to_owned(),
+                fee: Some(Uint128::new(50)),
+            },
+        )
+        .expect("Failed to update cw20 fee");
+        let receive_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "sender".to_owned(),
+            amount: Uint128::new(50),
+            msg: to_json_binary(&Cw20HookMsg::IbcTransferName {
+                channel_id: "2".to_owned(),
                 collection: "original".to_owned(),
                 token_id: "3".to_owned(),
-                sender_addr: "sender".to_owned(),
                 receiver_addr: "receiver".to_owned(),
+                refund_addr: None,
             })
-            .expect("Failed to serialize transfer message"),
+            .expect("Failed to serialize hook msg"),
+        });
+
+        // Act
+        let not_allowed_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("other", &[]),
+            receive_msg.to_owned(),
+        );
+        let allowed_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("allowed", &[]),
+            receive_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(
+                not_allowed_result,
+                Err(ContractError::Cw20NotAllowed { cw20_addr }) if cw20_addr == "other"
+            ),
+            "Should have rejected a cw20 not allowlisted"
+        );
+        assert!(allowed_result.is_ok(), "Failed to execute name transfer");
+        let in_flight = IN_FLIGHT
+            .load(&mocked_deps_mut.storage, ("2", "original", "3"))
+            .expect("Failed to load in-flight packet");
+        assert_eq!(
+            in_flight.fee,
+            Some(PaidFee {
+                payer: "sender".to_owned(),
+                amount: FeeAmount::Cw20 {
+                    cw20_addr: "allowed".to_owned(),
+                    amount: Uint128::new(50),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_name_with_relayer_fee() {
+        // Arrange
+        let mut mocked_deps_mut = mock_deps(OwnerOfResponse {
+            owner: "sender".to_owned(),
+            approvals: vec![],
+        });
+        let mocked_env = testing::mock_env();
+        let relayer_fee = RelayerFee {
+            recv_fee: vec![Coin::new(10, "uatom")],
+            ack_fee: vec![Coin::new(5, "uatom")],
+            timeout_fee: vec![Coin::new(5, "uatom")],
+        };
+        let transfer_msg = ExecuteMsg::IbcTransferName {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            relayer_fee: Some(relayer_fee.to_owned()),
+        };
+
+        // Act
+        let short_result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[Coin::new(15, "uatom")]),
+            transfer_msg.to_owned(),
+        );
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("sender", &[Coin::new(20, "uatom")]),
+            transfer_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(
+                short_result,
+                Err(ContractError::InsufficientRelayerFee { expected }) if expected == "20uatom"
+            ),
+            "Should have rejected funds short of the relayer fee"
+        );
+        assert!(result.is_ok(), "Failed to execute name transfer");
+        let received_response = result.unwrap();
+        assert_eq!(received_response.messages.len(), 3);
+        assert_eq!(
+            received_response.messages[1].msg,
+            pay_packet_fee_msg(
+                &relayer_fee,
+                &format!("wasm.{}", mocked_env.contract.address),
+                "2",
+                mocked_env.contract.address.as_str(),
+            )
+        );
+    }
+
+    #[test]
+    fn test_return_unclaimed_voucher() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mut mocked_env = testing::mock_env();
+        VOUCHERS_ADDR
+            .save(&mut mocked_deps_mut.storage, &"vouchers".to_owned())
+            .expect("Failed to save vouchers address");
+        PENDING_CLAIMS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "3",
+                &PendingClaim {
+                    receiver_addr: "receiver".to_owned(),
+                    return_addr: "sender".to_owned(),
+                    claim_until: mocked_env.block.time.plus_seconds(60),
+                },
+            )
+            .expect("Failed to save pending claim");
+        VOUCHER_IDS
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &"3".to_owned(),
+            )
+            .expect("Failed to save voucher id");
+        mocked_env.block.time = mocked_env.block.time.plus_seconds(60);
+        let return_unclaimed_msg = ExecuteMsg::ReturnUnclaimed {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("anyone", &[]),
+            return_unclaimed_msg,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to return unclaimed voucher");
+        let received_response = result.unwrap();
+        let expected_return_msg = IbcPacketMessage::ReturnName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: mocked_env.contract.address.to_string(),
+            receiver_addr: "sender".to_owned(),
+            refund_addr: None,
+        };
+        let expected_return_packet = IbcMsg::SendPacket {
+            channel_id: "2".to_owned(),
+            data: to_json_binary(&expected_return_msg).expect("Failed to serialize return msg"),
             timeout: IbcTimeout::with_timestamp(mocked_env.block.time.plus_seconds(120)),
         };
-        let expected_response = Response::default()
-            .add_message(expected_escrow_exec_msg)
-            .add_message(expected_transfer_packet);
-        assert_eq!(received_response, expected_response);
+        assert_eq!(
+            received_response.messages,
+            vec![SubMsg::new(expected_return_packet)]
+        );
+        let saved_in_flight = IN_FLIGHT
+            .load(&mocked_deps_mut.storage, ("2", "original", "3"))
+            .expect("Failed to load in flight");
+        assert_eq!(saved_in_flight.token, EscrowedToken::Voucher);
+        assert_eq!(
+            saved_in_flight.refund_addr,
+            mocked_env.contract.address.to_string()
+        );
+        assert!(PENDING_CLAIMS.has(&mocked_deps_mut.storage, "3"));
+    }
+
+    #[test]
+    fn test_return_unclaimed_voucher_within_claim_window() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        VOUCHERS_ADDR
+            .save(&mut mocked_deps_mut.storage, &"vouchers".to_owned())
+            .expect("Failed to save vouchers address");
+        PENDING_CLAIMS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "3",
+                &PendingClaim {
+                    receiver_addr: "receiver".to_owned(),
+                    return_addr: "sender".to_owned(),
+                    claim_until: mocked_env.block.time.plus_seconds(60),
+                },
+            )
+            .expect("Failed to save pending claim");
+        VOUCHER_IDS
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &"3".to_owned(),
+            )
+            .expect("Failed to save voucher id");
+        let return_unclaimed_msg = ExecuteMsg::ReturnUnclaimed {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("anyone", &[]),
+            return_unclaimed_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::ClaimWindowOpen { .. })),
+            "Should not return a voucher that can still be claimed"
+        );
+        assert!(PENDING_CLAIMS.has(&mocked_deps_mut.storage, "3"));
+    }
+
+    #[test]
+    fn test_claim_voucher_past_claim_window() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mut mocked_env = testing::mock_env();
+        VOUCHERS_ADDR
+            .save(&mut mocked_deps_mut.storage, &"vouchers".to_owned())
+            .expect("Failed to save vouchers address");
+        PENDING_CLAIMS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "3",
+                &PendingClaim {
+                    receiver_addr: "receiver".to_owned(),
+                    return_addr: "sender".to_owned(),
+                    claim_until: mocked_env.block.time.plus_seconds(60),
+                },
+            )
+            .expect("Failed to save pending claim");
+        VOUCHER_IDS
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("2", "original", "3"),
+                &"3".to_owned(),
+            )
+            .expect("Failed to save voucher id");
+        mocked_env.block.time = mocked_env.block.time.plus_seconds(60);
+        let claim_msg = ExecuteMsg::ClaimVoucher {
+            channel_id: "2".to_owned(),
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+
+        // Act
+        let result = super::execute(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            testing::mock_info("receiver", &[]),
+            claim_msg,
+        );
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::ClaimWindowPassed { .. })),
+            "Should not claim a voucher past its claim window"
+        );
     }
 }
@@ -1,3 +1,4 @@
+use crate::msg::ErrorCategory;
 use cosmwasm_std::StdError;
 use thiserror::Error;
 
@@ -11,8 +12,190 @@ pub enum ContractError {
     OrderedChannel,
     #[error("Channel {channel_id} already exists")]
     ChannelAlreadyExists { channel_id: String },
+    #[error("Counterparty port {port_id} on connection {connection_id} is not trusted")]
+    UntrustedCounterparty {
+        connection_id: String,
+        port_id: String,
+    },
     #[error("The channel cant be closed")]
     CantCloseChannel,
-    #[error("Only token owner can do this action")]
+    #[error("Only token owner or an approved operator can do this action")]
     OnlyOwner,
+    #[error("Invalid receiver address: {receiver_addr}")]
+    InvalidReceiver { receiver_addr: String },
+    #[error("Invalid refund address: {refund_addr}")]
+    InvalidRefundAddr { refund_addr: String },
+    #[error("No claimable refund for token {token_id} of {collection}")]
+    NoClaimableRefund {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Only the refund recipient can claim it")]
+    OnlyRefundRecipient,
+    #[error("No pending refund for reply")]
+    NoPendingRefund,
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+    #[error("Channel {channel_id} reached its supply cap of {supply_cap}")]
+    SupplyCapReached { channel_id: String, supply_cap: u64 },
+    #[error("Expected a fee of {expected}, got {paid}")]
+    IncorrectFee { expected: String, paid: String },
+    #[error("Funds do not cover the relayer fee of {expected}")]
+    InsufficientRelayerFee { expected: String },
+    #[error("Cw20 {cw20_addr} is not accepted as fee")]
+    Cw20NotAllowed { cw20_addr: String },
+    #[error("No fee is due, funds must not be sent")]
+    UnexpectedFunds,
+    #[error("No treasury to withdraw fees to")]
+    NoTreasury,
+    #[error("No fees to withdraw")]
+    NoFeesCollected,
+    #[error("Rate limit reached for {scope} transfers on channel {channel_id}")]
+    RateLimited { channel_id: String, scope: String },
+    #[error("Voucher {voucher_token_id} already exists")]
+    VoucherAlreadyExists { voucher_token_id: String },
+    #[error("Token {token_id} of {collection} is not escrowed")]
+    NameNotEscrowed {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Channel {channel_id} is closed")]
+    ChannelClosed { channel_id: String },
+    #[error("Channel {channel_id} still has packets in flight")]
+    ChannelHasInFlightPackets { channel_id: String },
+    #[error("Channel {channel_id} is not closed")]
+    ChannelNotClosed { channel_id: String },
+    #[error("Channel {channel_id} is open, names can only be force unescrowed once it is closed")]
+    CantForceUnescrow { channel_id: String },
+    #[error("No fee refunds to claim")]
+    NoClaimableFees,
+    #[error("No renewal deposit for {token_id} of {collection}")]
+    NoRenewalDeposit {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Renewals must be funded in a single denom")]
+    InvalidRenewalFunds,
+    #[error("Receiver address {receiver_addr} does not have expected prefix {expected}")]
+    InvalidReceiverPrefix {
+        receiver_addr: String,
+        expected: String,
+    },
+    #[error("Token {token_id} of {collection} not found")]
+    NameNotFound {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Token {token_id} of {collection} is already taken")]
+    NameAlreadyTaken {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Names of {collection} cannot be registered from other chains")]
+    RegistrationNotAllowed { collection: String },
+    #[error("Names of {collection} cannot be renewed from other chains")]
+    RenewalNotAllowed { collection: String },
+    #[error("Renewal costs {expected}, only {available} available")]
+    InsufficientRenewalFunds { expected: String, available: String },
+    #[error("Deposits must be made in {denom} only")]
+    InvalidDeposit { denom: String },
+    #[error("Voucher {voucher_token_id} not found")]
+    VoucherNotFound { voucher_token_id: String },
+    #[error("Voucher {voucher_token_id} has not expired")]
+    VoucherNotExpired { voucher_token_id: String },
+    #[error("Token {token_id} of {collection} has not expired")]
+    NameNotExpired {
+        collection: String,
+        token_id: String,
+    },
+    #[error("Voucher {voucher_token_id} is being returned")]
+    VoucherInFlight { voucher_token_id: String },
+    #[error("Names of {collection} cannot be teleported here")]
+    TeleportNotAllowed { collection: String },
+    #[error("No pending claim for voucher {voucher_token_id}")]
+    NoPendingClaim { voucher_token_id: String },
+    #[error("Only the receiver can claim voucher {voucher_token_id}")]
+    OnlyClaimReceiver { voucher_token_id: String },
+    #[error("Claim window of voucher {voucher_token_id} has passed")]
+    ClaimWindowPassed { voucher_token_id: String },
+    #[error("Voucher {voucher_token_id} can still be claimed")]
+    ClaimWindowOpen { voucher_token_id: String },
+}
+
+impl ContractError {
+    /// Numeric code of the error, carried in error acknowledgements so that the other chain can
+    /// match on it. Codes never change once assigned, new errors get new codes.
+    pub fn code(&self) -> u32 {
+        match self {
+            ContractError::Std(_) => 1,
+            ContractError::InvalidIbcVersion { .. } => 2,
+            ContractError::OrderedChannel => 3,
+            ContractError::ChannelAlreadyExists { .. } => 4,
+            ContractError::UntrustedCounterparty { .. } => 5,
+            ContractError::CantCloseChannel => 6,
+            ContractError::OnlyOwner => 7,
+            ContractError::InvalidReceiver { .. } => 8,
+            ContractError::InvalidRefundAddr { .. } => 9,
+            ContractError::NoClaimableRefund { .. } => 10,
+            ContractError::OnlyRefundRecipient => 11,
+            ContractError::NoPendingRefund => 12,
+            ContractError::UnknownReplyId { .. } => 13,
+            ContractError::SupplyCapReached { .. } => 14,
+            ContractError::IncorrectFee { .. } => 15,
+            ContractError::InsufficientRelayerFee { .. } => 16,
+            ContractError::Cw20NotAllowed { .. } => 17,
+            ContractError::UnexpectedFunds => 18,
+            ContractError::NoTreasury => 19,
+            ContractError::NoFeesCollected => 20,
+            ContractError::RateLimited { .. } => 21,
+            ContractError::VoucherAlreadyExists { .. } => 22,
+            ContractError::NameNotEscrowed { .. } => 23,
+            ContractError::ChannelClosed { .. } => 24,
+            ContractError::ChannelHasInFlightPackets { .. } => 25,
+            ContractError::ChannelNotClosed { .. } => 26,
+            ContractError::CantForceUnescrow { .. } => 27,
+            ContractError::InvalidReceiverPrefix { .. } => 28,
+            ContractError::NameNotFound { .. } => 29,
+            ContractError::NameAlreadyTaken { .. } => 30,
+            ContractError::RegistrationNotAllowed { .. } => 31,
+            ContractError::RenewalNotAllowed { .. } => 32,
+            ContractError::InsufficientRenewalFunds { .. } => 33,
+            ContractError::InvalidDeposit { .. } => 34,
+            ContractError::VoucherNotFound { .. } => 35,
+            ContractError::VoucherNotExpired { .. } => 36,
+            ContractError::NameNotExpired { .. } => 37,
+            ContractError::VoucherInFlight { .. } => 39,
+            ContractError::TeleportNotAllowed { .. } => 40,
+            ContractError::NoPendingClaim { .. } => 41,
+            ContractError::OnlyClaimReceiver { .. } => 42,
+            ContractError::ClaimWindowPassed { .. } => 43,
+            ContractError::ClaimWindowOpen { .. } => 44,
+            ContractError::NoClaimableFees => 46,
+            ContractError::NoRenewalDeposit { .. } => 48,
+            ContractError::InvalidRenewalFunds => 49,
+        }
+    }
+
+    /// Whether sending the same packet again later may succeed.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ContractError::Std(_)
+            | ContractError::SupplyCapReached { .. }
+            | ContractError::RateLimited { .. }
+            | ContractError::ChannelHasInFlightPackets { .. }
+            | ContractError::NoFeesCollected
+            | ContractError::InsufficientRenewalFunds { .. }
+            | ContractError::VoucherInFlight { .. } => ErrorCategory::Retriable,
+            _ => ErrorCategory::Permanent,
+        }
+    }
+
+    /// Message sent to the other chain. Standard errors may name internal types and storage
+    /// entries, so only their code is meaningful there.
+    pub fn ack_message(&self) -> String {
+        match self {
+            ContractError::Std(_) => "Internal error".to_owned(),
+            error => error.to_string(),
+        }
+    }
 }
@@ -0,0 +1,218 @@
+use crate::{
+    contract::FEE_REFUND_REPLY_ID,
+    error::ContractError,
+    msg::RelayerFee,
+    state::{
+        FeeAmount, PaidFee, PaidRelayerFee, CHANNEL_CONFIGS, CLAIMABLE_CW20_FEES, CLAIMABLE_FEES,
+        COLLECTED_CW20_FEES, COLLECTED_FEES, COLLECTION_FEES, CW20_FEES, FLAT_FEE,
+        PENDING_FEE_REFUNDS,
+    },
+};
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, MessageInfo, StdResult, Storage, SubMsg,
+    Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+
+/// Checks the funds sent with a transfer or a return against the fee due, which is the first
+/// found of the collection fee, the channel fee and the flat fee. The funds must match exactly.
+pub fn validate_fee(
+    storage: &dyn Storage,
+    info: &MessageInfo,
+    channel_id: &str,
+    collection: &str,
+) -> Result<Option<PaidFee>, ContractError> {
+    let fee = match COLLECTION_FEES.may_load(storage, collection)? {
+        Some(fee) => Some(fee),
+        None => match CHANNEL_CONFIGS
+            .may_load(storage, channel_id)?
+            .and_then(|config| config.fee)
+        {
+            Some(fee) => Some(fee),
+            None => FLAT_FEE.may_load(storage)?,
+        },
+    };
+    match fee.filter(|fee| !fee.amount.is_zero()) {
+        None if info.funds.is_empty() => Ok(None),
+        None => Err(ContractError::UnexpectedFunds),
+        Some(fee) if info.funds == [fee.to_owned()] => Ok(Some(PaidFee {
+            payer: info.sender.to_string(),
+            amount: FeeAmount::Native(fee),
+        })),
+        Some(fee) => Err(ContractError::IncorrectFee {
+            expected: fee.to_string(),
+            paid: info
+                .funds
+                .iter()
+                .map(Coin::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }),
+    }
+}
+
+/// Checks that cw20 tokens sent with a transfer or a return are allowlisted and match the fee
+/// set for them.
+pub fn validate_cw20_fee(
+    storage: &dyn Storage,
+    cw20_addr: &Addr,
+    cw20_msg: &Cw20ReceiveMsg,
+) -> Result<PaidFee, ContractError> {
+    let fee = CW20_FEES
+        .may_load(storage, cw20_addr.as_str())?
+        .ok_or_else(|| ContractError::Cw20NotAllowed {
+            cw20_addr: cw20_addr.to_string(),
+        })?;
+    if cw20_msg.amount != fee {
+        return Err(ContractError::IncorrectFee {
+            expected: format!("{fee}{cw20_addr}"),
+            paid: format!("{}{cw20_addr}", cw20_msg.amount),
+        });
+    }
+    Ok(PaidFee {
+        payer: cw20_msg.sender.to_owned(),
+        amount: FeeAmount::Cw20 {
+            cw20_addr: cw20_addr.to_string(),
+            amount: fee,
+        },
+    })
+}
+
+/// Takes the relayer fee out of the funds sent, leaving the funds meant for the bridge fee.
+pub fn split_relayer_fee(
+    mut info: MessageInfo,
+    relayer_fee: Option<RelayerFee>,
+) -> Result<(MessageInfo, Option<PaidRelayerFee>), ContractError> {
+    let Some(relayer_fee) = relayer_fee else {
+        return Ok((info, None));
+    };
+    let total = sum_coins([
+        &relayer_fee.recv_fee,
+        &relayer_fee.ack_fee,
+        &relayer_fee.timeout_fee,
+    ]);
+    let insufficient = || ContractError::InsufficientRelayerFee {
+        expected: total
+            .iter()
+            .map(Coin::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    };
+    for coin in total.iter() {
+        let fund = info
+            .funds
+            .iter_mut()
+            .find(|fund| fund.denom == coin.denom)
+            .ok_or_else(insufficient)?;
+        fund.amount = fund
+            .amount
+            .checked_sub(coin.amount)
+            .map_err(|_| insufficient())?;
+    }
+    info.funds.retain(|fund| !fund.amount.is_zero());
+    let paid = PaidRelayerFee {
+        payer: info.sender.to_string(),
+        fee: relayer_fee,
+    };
+    Ok((info, Some(paid)))
+}
+
+/// Forwards to the payer the part of the relayer fee that the fee middleware refunds to this
+/// contract: the timeout fee once the packet is acknowledged, the others once it timed out.
+pub fn refund_unused_relayer_fee(paid: PaidRelayerFee, acknowledged: bool) -> Option<BankMsg> {
+    let unused = if acknowledged {
+        sum_coins([&paid.fee.timeout_fee])
+    } else {
+        sum_coins([&paid.fee.recv_fee, &paid.fee.ack_fee])
+    };
+    if unused.is_empty() {
+        return None;
+    }
+    Some(BankMsg::Send {
+        to_address: paid.payer,
+        amount: unused,
+    })
+}
+
+/// Forwards to the payer the whole relayer fee of a packet still in flight when its channel
+/// closed, which the fee middleware then refunds to this contract.
+pub fn refund_closed_relayer_fee(paid: PaidRelayerFee) -> Option<BankMsg> {
+    let unused = sum_coins([&paid.fee.recv_fee, &paid.fee.ack_fee, &paid.fee.timeout_fee]);
+    if unused.is_empty() {
+        return None;
+    }
+    Some(BankMsg::Send {
+        to_address: paid.payer,
+        amount: unused,
+    })
+}
+
+/// Adds up coins by denom, leaving out zero amounts.
+fn sum_coins<const N: usize>(coin_lists: [&Vec<Coin>; N]) -> Vec<Coin> {
+    let mut total: Vec<Coin> = vec![];
+    for coin in coin_lists.into_iter().flatten() {
+        match total.iter_mut().find(|sum| sum.denom == coin.denom) {
+            Some(sum) => sum.amount += coin.amount,
+            None => total.push(coin.to_owned()),
+        }
+    }
+    total.retain(|coin| !coin.amount.is_zero());
+    total
+}
+
+/// Returns the fee to its payer, for packets that did not go through.
+pub fn refund_fee(fee: PaidFee) -> StdResult<CosmosMsg> {
+    Ok(match fee.amount {
+        FeeAmount::Native(coin) => BankMsg::Send {
+            to_address: fee.payer,
+            amount: vec![coin],
+        }
+        .into(),
+        FeeAmount::Cw20 { cw20_addr, amount } => WasmMsg::Execute {
+            contract_addr: cw20_addr,
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: fee.payer,
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
+}
+
+/// Returns the fee to its payer as a submessage whose reply makes the fee claimable by the payer
+/// if it fails, so that a payer unable to receive it cannot block the packet lifecycle.
+pub fn refund_fee_submsg(storage: &mut dyn Storage, fee: PaidFee) -> StdResult<SubMsg> {
+    PENDING_FEE_REFUNDS.push_back(storage, &fee)?;
+    Ok(SubMsg::reply_always(refund_fee(fee)?, FEE_REFUND_REPLY_ID))
+}
+
+/// Keeps a fee whose refund failed until its payer claims it.
+pub fn keep_claimable_fee(storage: &mut dyn Storage, fee: PaidFee) -> Result<(), ContractError> {
+    match fee.amount {
+        FeeAmount::Native(coin) => {
+            CLAIMABLE_FEES.update(storage, (&fee.payer, &coin.denom), add_to(coin.amount))?;
+        }
+        FeeAmount::Cw20 { cw20_addr, amount } => {
+            CLAIMABLE_CW20_FEES.update(storage, (&fee.payer, &cw20_addr), add_to(amount))?;
+        }
+    }
+    Ok(())
+}
+
+fn add_to(amount: Uint128) -> impl FnOnce(Option<Uint128>) -> StdResult<Uint128> {
+    move |collected| Ok(collected.unwrap_or_default().checked_add(amount)?)
+}
+
+/// Keeps the fee of a delivered packet until it is withdrawn to the treasury.
+pub fn collect_fee(storage: &mut dyn Storage, fee: PaidFee) -> Result<(), ContractError> {
+    match fee.amount {
+        FeeAmount::Native(coin) => {
+            COLLECTED_FEES.update(storage, &coin.denom, add_to(coin.amount))?;
+        }
+        FeeAmount::Cw20 { cw20_addr, amount } => {
+            COLLECTED_CW20_FEES.update(storage, &cw20_addr, add_to(amount))?;
+        }
+    }
+    Ok(())
+}
@@ -1,28 +1,56 @@
-use crate::error::ContractError;
+use crate::{
+    error::ContractError,
+    state::{
+        ANY_COUNTERPARTY_ALLOWED, CLOSED_CHANNELS, CLOSE_REQUESTS, COUNTERPARTY_CHANNELS,
+        ONE_CHANNEL_PER_COUNTERPARTY, TRUSTED_COUNTERPARTIES,
+    },
+};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    entry_point, DepsMut, Env, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
-    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder,
+    entry_point, from_json, DepsMut, Env, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, Order,
 };
 
 pub const IBC_CUSTOM_PROTOCOL_VERSION: &str = "ibc-name-transfer-1.0";
+pub const ICS29_FEE_VERSION: &str = "ics29-1";
+
+/// Channel version negotiated through the ICS-29 fee middleware.
+#[cw_serde]
+struct FeeVersion {
+    fee_version: String,
+    app_version: String,
+}
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_open(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
     msg: IbcChannelOpenMsg,
 ) -> Result<IbcChannelOpenResponse, ContractError> {
     validate_order_and_version(msg.channel(), msg.counterparty_version())?;
+    validate_counterparty(&deps, msg.channel())?;
+    validate_one_channel(&deps, msg.channel())?;
     Ok(None)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_connect(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
     msg: IbcChannelConnectMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     validate_order_and_version(msg.channel(), msg.counterparty_version())?;
+    // Several handshakes with the same counterparty may have passed the open step before any
+    // of them connected, so the one channel rule is checked again here.
+    validate_one_channel(&deps, msg.channel())?;
+    COUNTERPARTY_CHANNELS.save(
+        deps.storage,
+        (
+            &msg.channel().connection_id,
+            &msg.channel().counterparty_endpoint.port_id,
+        ),
+        &msg.channel().endpoint.channel_id,
+    )?;
     Ok(IbcBasicResponse::new()
         .add_attribute("method", "ibc_channel_connect")
         .add_attribute("channel", &msg.channel().endpoint.channel_id)
@@ -31,16 +59,33 @@ pub fn ibc_channel_connect(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_channel_close(
-    _deps: DepsMut,
-    _env: Env,
+    deps: DepsMut,
+    env: Env,
     msg: IbcChannelCloseMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     match msg {
-        IbcChannelCloseMsg::CloseInit { channel: _ } => Err(ContractError::CantCloseChannel {}),
-        IbcChannelCloseMsg::CloseConfirm { channel: _ } => Ok(IbcBasicResponse::default()),
+        IbcChannelCloseMsg::CloseInit { channel } => {
+            if !CLOSE_REQUESTS.has(deps.storage, &channel.endpoint.channel_id) {
+                return Err(ContractError::CantCloseChannel {});
+            }
+            CLOSE_REQUESTS.remove(deps.storage, &channel.endpoint.channel_id);
+            mark_channel_closed(deps, env, &channel)
+        }
+        IbcChannelCloseMsg::CloseConfirm { channel } => mark_channel_closed(deps, env, &channel),
     }
 }
 
+fn mark_channel_closed(
+    deps: DepsMut,
+    env: Env,
+    channel: &IbcChannel,
+) -> Result<IbcBasicResponse, ContractError> {
+    CLOSED_CHANNELS.save(deps.storage, &channel.endpoint.channel_id, &env.block.time)?;
+    Ok(IbcBasicResponse::new()
+        .add_attribute("method", "ibc_channel_close")
+        .add_attribute("channel", &channel.endpoint.channel_id))
+}
+
 fn validate_order_and_version(
     channel: &IbcChannel,
     counterparty_version: Option<&str>,
@@ -48,14 +93,14 @@ fn validate_order_and_version(
     if channel.order != IbcOrder::Unordered {
         return Err(ContractError::OrderedChannel);
     }
-    if channel.version != IBC_CUSTOM_PROTOCOL_VERSION {
+    if unwrap_app_version(&channel.version) != IBC_CUSTOM_PROTOCOL_VERSION {
         return Err(ContractError::InvalidIbcVersion {
             actual: channel.version.clone(),
             expected: IBC_CUSTOM_PROTOCOL_VERSION.to_string(),
         });
     }
     if let Some(counterparty_version) = counterparty_version {
-        if counterparty_version != IBC_CUSTOM_PROTOCOL_VERSION {
+        if unwrap_app_version(counterparty_version) != IBC_CUSTOM_PROTOCOL_VERSION {
             return Err(ContractError::InvalidIbcVersion {
                 actual: counterparty_version.to_string(),
                 expected: IBC_CUSTOM_PROTOCOL_VERSION.to_string(),
@@ -64,3 +109,186 @@ fn validate_order_and_version(
     }
     Ok(())
 }
+
+/// Returns the application version, wrapped or not by the ICS-29 fee middleware.
+fn unwrap_app_version(version: &str) -> String {
+    match from_json::<FeeVersion>(version.as_bytes()) {
+        Ok(fee_version) if fee_version.fee_version == ICS29_FEE_VERSION => fee_version.app_version,
+        _ => version.to_owned(),
+    }
+}
+
+fn validate_counterparty(deps: &DepsMut, channel: &IbcChannel) -> Result<(), ContractError> {
+    let connection_id = &channel.connection_id;
+    let port_id = &channel.counterparty_endpoint.port_id;
+    if TRUSTED_COUNTERPARTIES.has(deps.storage, (connection_id, port_id)) {
+        return Ok(());
+    }
+    let has_trusted = TRUSTED_COUNTERPARTIES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    let any_allowed = ANY_COUNTERPARTY_ALLOWED
+        .may_load(deps.storage)?
+        .unwrap_or(false);
+    if has_trusted || !any_allowed {
+        return Err(ContractError::UntrustedCounterparty {
+            connection_id: connection_id.to_owned(),
+            port_id: port_id.to_owned(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_one_channel(deps: &DepsMut, channel: &IbcChannel) -> Result<(), ContractError> {
+    let connection_id = &channel.connection_id;
+    let port_id = &channel.counterparty_endpoint.port_id;
+    if ONE_CHANNEL_PER_COUNTERPARTY
+        .may_load(deps.storage)?
+        .unwrap_or(false)
+    {
+        if let Some(channel_id) =
+            COUNTERPARTY_CHANNELS.may_load(deps.storage, (connection_id, port_id))?
+        {
+            if channel_id != channel.endpoint.channel_id
+                && !CLOSED_CHANNELS.has(deps.storage, &channel_id)
+            {
+                return Err(ContractError::ChannelAlreadyExists { channel_id });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::ContractError,
+        ibc::channel::{
+            ibc_channel_connect, ibc_channel_open, IBC_CUSTOM_PROTOCOL_VERSION, ICS29_FEE_VERSION,
+        },
+        state::{
+            ANY_COUNTERPARTY_ALLOWED, COUNTERPARTY_CHANNELS, ONE_CHANNEL_PER_COUNTERPARTY,
+            TRUSTED_COUNTERPARTIES,
+        },
+    };
+    use cosmwasm_std::{testing, Empty, IbcOrder};
+
+    #[test]
+    fn test_channel_open_untrusted_counterparty() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        TRUSTED_COUNTERPARTIES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("connection-trusted", "their-port"),
+                &Empty {},
+            )
+            .expect("Failed to save trusted counterparty");
+        let open_msg = testing::mock_ibc_channel_open_init(
+            "channel-1",
+            IbcOrder::Unordered,
+            IBC_CUSTOM_PROTOCOL_VERSION,
+        );
+
+        // Act
+        let result = ibc_channel_open(mocked_deps_mut.as_mut(), mocked_env, open_msg);
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::UntrustedCounterparty { .. })),
+            "Should have rejected untrusted counterparty"
+        );
+    }
+
+    #[test]
+    fn test_channel_open_without_trusted_counterparty() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let open_msg = testing::mock_ibc_channel_open_init(
+            "channel-1",
+            IbcOrder::Unordered,
+            IBC_CUSTOM_PROTOCOL_VERSION,
+        );
+        let refused = ibc_channel_open(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            open_msg.to_owned(),
+        );
+        ANY_COUNTERPARTY_ALLOWED
+            .save(&mut mocked_deps_mut.storage, &true)
+            .expect("Failed to allow any counterparty");
+
+        // Act
+        let result = ibc_channel_open(mocked_deps_mut.as_mut(), mocked_env, open_msg);
+
+        // Assert
+        assert!(
+            matches!(refused, Err(ContractError::UntrustedCounterparty { .. })),
+            "Should only allow any counterparty once opted in"
+        );
+        assert!(result.is_ok(), "Failed to open channel");
+    }
+
+    #[test]
+    fn test_channel_connect_second_channel_with_counterparty() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ONE_CHANNEL_PER_COUNTERPARTY
+            .save(&mut mocked_deps_mut.storage, &true)
+            .expect("Failed to save one channel rule");
+        let first_connect_msg = testing::mock_ibc_channel_connect_ack(
+            "channel-1",
+            IbcOrder::Unordered,
+            IBC_CUSTOM_PROTOCOL_VERSION,
+        );
+        ibc_channel_connect(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            first_connect_msg,
+        )
+        .expect("Failed to connect first channel");
+        let second_connect_msg = testing::mock_ibc_channel_connect_ack(
+            "channel-2",
+            IbcOrder::Unordered,
+            IBC_CUSTOM_PROTOCOL_VERSION,
+        );
+
+        // Act
+        let result = ibc_channel_connect(mocked_deps_mut.as_mut(), mocked_env, second_connect_msg);
+
+        // Assert
+        assert!(
+            matches!(result, Err(ContractError::ChannelAlreadyExists { .. })),
+            "Should not connect a second channel with the same counterparty"
+        );
+        let connected_channel = COUNTERPARTY_CHANNELS
+            .load(&mocked_deps_mut.storage, ("connection-2", "their_port"))
+            .expect("Failed to load counterparty channel");
+        assert_eq!(connected_channel, "channel-1");
+    }
+
+    #[test]
+    fn test_channel_open_fee_wrapped_version() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ANY_COUNTERPARTY_ALLOWED
+            .save(&mut mocked_deps_mut.storage, &true)
+            .expect("Failed to allow any counterparty");
+        let fee_version = format!(
+            r#"{{"fee_version":"{ICS29_FEE_VERSION}","app_version":"{IBC_CUSTOM_PROTOCOL_VERSION}"}}"#
+        );
+        let open_msg =
+            testing::mock_ibc_channel_open_init("channel-1", IbcOrder::Unordered, &fee_version);
+
+        // Act
+        let result = ibc_channel_open(mocked_deps_mut.as_mut(), mocked_env, open_msg);
+
+        // Assert
+        assert!(result.is_ok(), "Failed to open fee-wrapped channel");
+    }
+}
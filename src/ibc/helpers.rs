@@ -1,14 +1,37 @@
+use crate::{msg::VoucherIdScheme, state::VOUCHER_IDS};
+use cosmwasm_std::{StdResult, Storage};
 use sha2::{Digest, Sha256};
 
 fn bytes_to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
-pub fn compute_voucher_token_id(
-    channel_id: &String,
-    collection: &String,
-    token_id: &String,
-) -> String {
+pub fn compute_voucher_token_id(channel_id: &str, collection: &str, token_id: &str) -> String {
     let voucher_id = format!("transfer_name/ibc/{channel_id}/{collection}/{token_id}");
     bytes_to_hex(&Sha256::digest(voucher_id.as_bytes()))
 }
+
+pub fn format_voucher_token_id(
+    scheme: &VoucherIdScheme,
+    channel_id: &str,
+    collection: &str,
+    token_id: &str,
+) -> String {
+    match scheme {
+        VoucherIdScheme::Hash => compute_voucher_token_id(channel_id, collection, token_id),
+        VoucherIdScheme::TokenId => token_id.to_owned(),
+        VoucherIdScheme::ChannelTokenId => format!("{channel_id}/{token_id}"),
+    }
+}
+
+/// Id of the voucher of a name, whatever the scheme it was minted with.
+pub fn load_voucher_token_id(
+    storage: &dyn Storage,
+    channel_id: &str,
+    collection: &str,
+    token_id: &str,
+) -> StdResult<String> {
+    Ok(VOUCHER_IDS
+        .may_load(storage, (channel_id, collection, token_id))?
+        .unwrap_or_else(|| compute_voucher_token_id(channel_id, collection, token_id)))
+}
@@ -0,0 +1,94 @@
+use crate::msg::RelayerFee;
+use cosmwasm_std::{Binary, Coin, CosmosMsg};
+
+const MSG_PAY_PACKET_FEE_TYPE_URL: &str = "/ibc.applications.fee.v1.MsgPayPacketFee";
+
+/// Builds a `MsgPayPacketFee` escrowing `fee` for the next packet sent on the channel. It must be
+/// dispatched before the packet, and `signer` pays the fee and gets back what is not used.
+pub fn pay_packet_fee_msg(
+    fee: &RelayerFee,
+    source_port_id: &str,
+    source_channel_id: &str,
+    signer: &str,
+) -> CosmosMsg {
+    let mut value = vec![];
+    encode_bytes(1, &encode_fee(fee), &mut value);
+    encode_bytes(2, source_port_id.as_bytes(), &mut value);
+    encode_bytes(3, source_channel_id.as_bytes(), &mut value);
+    encode_bytes(4, signer.as_bytes(), &mut value);
+    CosmosMsg::Stargate {
+        type_url: MSG_PAY_PACKET_FEE_TYPE_URL.to_owned(),
+        value: Binary::from(value),
+    }
+}
+
+fn encode_fee(fee: &RelayerFee) -> Vec<u8> {
+    let mut buf = vec![];
+    for (field, coins) in [(1, &fee.recv_fee), (2, &fee.ack_fee), (3, &fee.timeout_fee)] {
+        for coin in coins {
+            encode_bytes(field, &encode_coin(coin), &mut buf);
+        }
+    }
+    buf
+}
+
+fn encode_coin(coin: &Coin) -> Vec<u8> {
+    let mut buf = vec![];
+    encode_bytes(1, coin.denom.as_bytes(), &mut buf);
+    encode_bytes(2, coin.amount.to_string().as_bytes(), &mut buf);
+    buf
+}
+
+/// Encodes a length-delimited protobuf field.
+fn encode_bytes(field: u64, bytes: &[u8], buf: &mut Vec<u8>) {
+    encode_varint((field << 3) | 2, buf);
+    encode_varint(bytes.len() as u64, buf);
+    buf.extend_from_slice(bytes);
+}
+
+fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ibc::ics29::pay_packet_fee_msg, msg::RelayerFee};
+    use cosmwasm_std::{Binary, Coin, CosmosMsg};
+
+    #[test]
+    fn test_pay_packet_fee_msg() {
+        // Arrange
+        let fee = RelayerFee {
+            recv_fee: vec![Coin::new(1, "u")],
+            ack_fee: vec![],
+            timeout_fee: vec![Coin::new(2, "u")],
+        };
+
+        // Act
+        let msg = pay_packet_fee_msg(&fee, "wasm.c", "channel-0", "c");
+
+        // Assert
+        let expected_value = [
+            [10, 16].as_slice(),
+            &[10, 6, 10, 1, b'u', 18, 1, b'1'],
+            &[26, 6, 10, 1, b'u', 18, 1, b'2'],
+            &[18, 6],
+            b"wasm.c",
+            &[26, 9],
+            b"channel-0",
+            &[34, 1, b'c'],
+        ]
+        .concat();
+        assert_eq!(
+            msg,
+            CosmosMsg::Stargate {
+                type_url: "/ibc.applications.fee.v1.MsgPayPacketFee".to_owned(),
+                value: Binary::from(expected_value),
+            }
+        );
+    }
+}
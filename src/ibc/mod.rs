@@ -1,3 +1,4 @@
 pub mod channel;
 pub mod helpers;
+pub mod ics29;
 pub mod packet;
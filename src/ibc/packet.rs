@@ -1,15 +1,35 @@
-use super::helpers::compute_voucher_token_id;
+use super::helpers::{compute_voucher_token_id, format_voucher_token_id, load_voucher_token_id};
 use crate::{
+    contract::{
+        query_expiry, remint_teleported_msg, validate_supply_cap, CALLBACK_REPLY_ID,
+        REFUND_REPLY_ID,
+    },
     error::ContractError,
-    msg::{CollectionExecuteMsg, IbcPacketMessage},
-    state::VOUCHERS_ADDR,
+    fees,
+    msg::{
+        AckError, AckSuccess, CollectionExecuteMsg, CollectionQueryMsg, ExpiryPolicy,
+        IbcPacketMessage, JsonValue, MetadataExecuteMsg, NameResolution, NameserviceExecuteMsg,
+        RenewalPayment, ResolveNameCallbackMsg, ResolveNameResult, TransferMode,
+    },
+    rate_limit,
+    state::{
+        remove_escrowed_name, remove_voucher_trace, save_escrowed_name, save_voucher_trace, Escrow,
+        EscrowedToken, InFlight, PendingClaim, Refund, Remint, VoucherTrace, CHANNEL_CONFIGS,
+        CLOSED_CHANNELS, ESCROWED_NAMES, EXPIRY_POLICIES, IN_FLIGHT, LEGACY_PACKETS_BEFORE,
+        PENDING_CLAIMS, PENDING_REFUNDS, REGISTRABLE_COLLECTIONS, RENEWAL_CONFIGS,
+        RENEWAL_DEPOSITS, RENEWAL_FUNDS, TELEPORT_REMOTES, VOUCHERS_ADDR, VOUCHER_ID_SCHEMES,
+        VOUCHER_TRACES,
+    },
 };
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_json, to_json_binary, DepsMut, Env, Event, IbcBasicResponse, IbcPacketAckMsg,
-    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, StdAck, WasmMsg,
+    from_json, to_json_binary, to_json_string, Addr, Coin, DepsMut, Env, Event, IbcBasicResponse,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout,
+    Order, QueryRequest, StdAck, StdResult, Storage, SubMsg, Timestamp, Uint128, WasmMsg,
+    WasmQuery,
 };
+use cw721::msg::{NftInfoResponse, OwnerOfResponse};
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_packet_receive(
@@ -20,11 +40,45 @@ pub fn ibc_packet_receive(
     let ibc_msg = from_json::<IbcPacketMessage>(msg.packet.data)?;
     let response = match ibc_msg {
         IbcPacketMessage::TransferName {
+            collection,
+            token_id,
+            sender_addr,
+            receiver_addr,
+            refund_addr,
+            expires_at,
+            mode,
+        } => match mode {
+            TransferMode::Escrow => ibc_receive_transfer_name(
+                deps,
+                env,
+                msg.packet.dest.channel_id,
+                collection,
+                token_id,
+                receiver_addr,
+                refund_addr.unwrap_or(sender_addr),
+                expires_at,
+            ),
+            TransferMode::Teleport {
+                token_uri,
+                extension,
+            } => ibc_receive_teleport_name(
+                deps,
+                env,
+                msg.packet.dest.channel_id,
+                collection,
+                token_id,
+                receiver_addr,
+                token_uri,
+                extension,
+            ),
+        },
+        IbcPacketMessage::ReturnName {
             collection,
             token_id,
             sender_addr: _sender_addr,
             receiver_addr,
-        } => ibc_receive_transfer_name(
+            refund_addr: _refund_addr,
+        } => ibc_receive_return_name(
             deps,
             env,
             msg.packet.dest.channel_id,
@@ -32,69 +86,606 @@ pub fn ibc_packet_receive(
             token_id,
             receiver_addr,
         ),
-        IbcPacketMessage::ReturnName {
+        IbcPacketMessage::ResolveName {
+            collection,
+            token_id,
+            requester: _requester,
+        } => ibc_receive_resolve_name(deps, collection, token_id),
+        IbcPacketMessage::UpdateNameRecord {
             collection,
             token_id,
             sender_addr: _sender_addr,
-            receiver_addr,
-        } => ibc_receive_return_name(deps, env, collection, token_id, receiver_addr),
+            token_uri,
+            extension,
+        } => ibc_receive_update_name_record(
+            deps,
+            msg.packet.dest.channel_id,
+            collection,
+            token_id,
+            token_uri,
+            extension,
+        ),
+        IbcPacketMessage::RegisterName {
+            collection,
+            token_id,
+            owner_addr: _owner_addr,
+        } => ibc_receive_register_name(deps, env, msg.packet.dest.channel_id, collection, token_id),
+        IbcPacketMessage::RenewName {
+            collection,
+            token_id,
+            sender_addr: _sender_addr,
+        } => ibc_receive_renew_name(deps, msg.packet.dest.channel_id, collection, token_id),
+        IbcPacketMessage::SyncMetadata {
+            collection,
+            token_id,
+            token_uri,
+            extension,
+        } => ibc_receive_sync_metadata(
+            deps,
+            msg.packet.dest.channel_id,
+            collection,
+            token_id,
+            token_uri,
+            extension,
+        ),
+        IbcPacketMessage::ExpireVoucher {
+            collection,
+            token_id,
+        } => {
+            ibc_receive_expire_voucher(deps, env, msg.packet.dest.channel_id, collection, token_id)
+        }
     };
     match response {
         Ok(response) => Ok(response),
         Err(error) => Ok(IbcReceiveResponse::new()
             .add_attribute("method", "ibc_packet_receive")
             .add_attribute("error", error.to_string())
-            .set_ack(StdAck::Error(error.to_string()))),
+            .set_ack(error_ack(&error)?)),
     }
 }
 
+fn error_ack(error: &ContractError) -> StdResult<StdAck> {
+    Ok(StdAck::Error(to_json_string(&AckError {
+        code: error.code(),
+        category: error.category(),
+        message: error.ack_message(),
+    })?))
+}
+
+/// With an inbox on the channel, the voucher is minted to this contract and held for the receiver
+/// to claim, `return_addr` getting the name back on the origin chain if they do not.
+#[allow(clippy::too_many_arguments)]
 fn ibc_receive_transfer_name(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     channel_id: String,
     collection: String,
     token_id: String,
     receiver_addr: String,
+    return_addr: String,
+    expires_at: Option<Timestamp>,
 ) -> Result<IbcReceiveResponse, ContractError> {
+    let receiver_addr = validate_receiver(&deps, &receiver_addr)?;
     let voucher_collection = VOUCHERS_ADDR.load(deps.storage)?;
-    let voucher_token_id = compute_voucher_token_id(&channel_id, &collection, &token_id);
+    let voucher_token_id = new_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    rate_limit::consume_inbound(deps.storage, env.block.time, &channel_id)?;
+    let inbox_claim_seconds = CHANNEL_CONFIGS
+        .may_load(deps.storage, &channel_id)?
+        .and_then(|config| config.inbox_claim_seconds);
+    let (owner, inbox_event) = match inbox_claim_seconds {
+        Some(claim_seconds) => {
+            let claim = PendingClaim {
+                receiver_addr: receiver_addr.to_string(),
+                return_addr,
+                claim_until: env.block.time.plus_seconds(claim_seconds),
+            };
+            PENDING_CLAIMS.save(deps.storage, &voucher_token_id, &claim)?;
+            let inbox_event = Event::new("my-ics-name-inbox")
+                .add_attribute("voucher-token-id", voucher_token_id.to_owned())
+                .add_attribute("receiver", claim.receiver_addr)
+                .add_attribute("claim-until", claim.claim_until.to_string());
+            (env.contract.address.to_string(), Some(inbox_event))
+        }
+        None => (receiver_addr.to_string(), None),
+    };
+    let (mint_exec_msg, mint_event) = mint_voucher(
+        deps.storage,
+        &voucher_collection,
+        &voucher_token_id,
+        VoucherTrace {
+            channel_id,
+            collection,
+            token_id,
+            expires_at,
+        },
+        owner,
+    )?;
+    let ack = AckSuccess::VoucherMinted {
+        voucher_collection,
+        voucher_token_id,
+    };
+    Ok(IbcReceiveResponse::default()
+        .add_message(mint_exec_msg)
+        .add_event(mint_event)
+        .add_events(inbox_event)
+        .set_ack(StdAck::success(to_json_binary(&ack)?)))
+}
+
+/// Mints a teleported name in the local counterpart of its collection.
+#[allow(clippy::too_many_arguments)]
+fn ibc_receive_teleport_name(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    receiver_addr: String,
+    token_uri: Option<String>,
+    extension: Option<JsonValue>,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let receiver_addr = validate_receiver(&deps, &receiver_addr)?;
+    let local_collection = TELEPORT_REMOTES
+        .may_load(deps.storage, (&channel_id, &collection))?
+        .ok_or_else(|| ContractError::TeleportNotAllowed {
+            collection: collection.to_owned(),
+        })?;
+    rate_limit::consume_inbound(deps.storage, env.block.time, &channel_id)?;
+    let mint_msg = MetadataExecuteMsg::Mint {
+        token_id: token_id.to_owned(),
+        owner: receiver_addr.to_string(),
+        token_uri,
+        extension,
+    };
+    let mint_exec_msg = WasmMsg::Execute {
+        contract_addr: local_collection.to_owned(),
+        msg: to_json_binary(&mint_msg)?,
+        funds: vec![],
+    };
+    let teleport_event = Event::new("my-ics-name-teleport")
+        .add_attribute("channel", channel_id)
+        .add_attribute("remote-collection", collection)
+        .add_attribute("collection", local_collection.to_owned())
+        .add_attribute("token-id", token_id.to_owned());
+    let ack = AckSuccess::NameTeleported {
+        collection: local_collection,
+        token_id,
+    };
+    Ok(IbcReceiveResponse::new()
+        .add_message(mint_exec_msg)
+        .add_event(teleport_event)
+        .set_ack(StdAck::success(to_json_binary(&ack)?)))
+}
+
+/// Id of a new voucher for a name, in the scheme set for its channel and collection. Readable ids
+/// are checked against the vouchers already minted.
+pub(crate) fn new_voucher_token_id(
+    storage: &dyn Storage,
+    channel_id: &str,
+    collection: &str,
+    token_id: &str,
+) -> Result<String, ContractError> {
+    let voucher_token_id = load_voucher_token_id(storage, channel_id, collection, token_id)?;
+    if VOUCHER_TRACES.has(storage, &voucher_token_id) {
+        return Err(ContractError::VoucherAlreadyExists { voucher_token_id });
+    }
+    let scheme = VOUCHER_ID_SCHEMES
+        .may_load(storage, (channel_id, collection))?
+        .unwrap_or_default();
+    let voucher_token_id = format_voucher_token_id(&scheme, channel_id, collection, token_id);
+    if VOUCHER_TRACES.has(storage, &voucher_token_id) {
+        return Err(ContractError::VoucherAlreadyExists { voucher_token_id });
+    }
+    Ok(voucher_token_id)
+}
+
+/// Records the trace of a new voucher and mints it to `owner`.
+fn mint_voucher(
+    storage: &mut dyn Storage,
+    voucher_collection: &str,
+    voucher_token_id: &str,
+    trace: VoucherTrace,
+    owner: String,
+) -> Result<(WasmMsg, Event), ContractError> {
+    save_voucher_trace(storage, voucher_token_id, &trace)?;
     let mint_msg = CollectionExecuteMsg::Mint {
-        token_id: voucher_token_id,
-        owner: receiver_addr,
+        token_id: voucher_token_id.to_owned(),
+        owner,
         token_uri: None,
         extension: None,
     };
     let mint_exec_msg = WasmMsg::Execute {
-        contract_addr: voucher_collection,
+        contract_addr: voucher_collection.to_owned(),
         msg: to_json_binary(&mint_msg)?,
         funds: vec![],
     };
     let mint_event = Event::new("my-ics-name-voucher-mint")
-        .add_attribute("channel", channel_id)
-        .add_attribute("original-collection", collection)
-        .add_attribute("token-id", token_id);
-    Ok(IbcReceiveResponse::default()
-        .add_message(mint_exec_msg)
-        .add_event(mint_event))
+        .add_attribute("channel", trace.channel_id)
+        .add_attribute("original-collection", trace.collection)
+        .add_attribute("token-id", trace.token_id);
+    Ok((mint_exec_msg, mint_event))
 }
 
 fn ibc_receive_return_name(
-    _deps: DepsMut,
-    _env: Env,
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
     collection: String,
     token_id: String,
     receiver_addr: String,
 ) -> Result<IbcReceiveResponse, ContractError> {
+    let receiver_addr = validate_receiver(&deps, &receiver_addr)?;
+    let escrow_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+    if !ESCROWED_NAMES.has(deps.storage, escrow_key) {
+        return Err(ContractError::NameNotEscrowed {
+            collection,
+            token_id,
+        });
+    }
+    rate_limit::consume_inbound(deps.storage, env.block.time, &channel_id)?;
+    remove_escrowed_name(deps.storage, escrow_key)?;
     let unescrow_msg = CollectionExecuteMsg::TransferNft {
         token_id,
-        recipient: receiver_addr,
+        recipient: receiver_addr.to_string(),
     };
     let unescrow_wasm_msg = WasmMsg::Execute {
         contract_addr: collection,
         msg: to_json_binary(&unescrow_msg)?,
         funds: vec![],
     };
-    Ok(IbcReceiveResponse::new().add_message(unescrow_wasm_msg))
+    let ack = AckSuccess::NameUnescrowed {
+        recipient: receiver_addr.to_string(),
+    };
+    Ok(IbcReceiveResponse::new()
+        .add_message(unescrow_wasm_msg)
+        .set_ack(StdAck::success(to_json_binary(&ack)?)))
+}
+
+fn ibc_receive_resolve_name(
+    deps: DepsMut,
+    collection: String,
+    token_id: String,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let not_found = || ContractError::NameNotFound {
+        collection: collection.to_owned(),
+        token_id: token_id.to_owned(),
+    };
+    let owner_of = deps
+        .querier
+        .query::<OwnerOfResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionQueryMsg::OwnerOf {
+                token_id: token_id.to_owned(),
+                include_expired: None,
+            })?,
+        }))
+        .map_err(|_| not_found())?;
+    let nft_info = deps
+        .querier
+        .query::<NftInfoResponse<Option<JsonValue>>>(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionQueryMsg::NftInfo {
+                token_id: token_id.to_owned(),
+            })?,
+        }))
+        .map_err(|_| not_found())?;
+    let ack = AckSuccess::NameResolved(NameResolution {
+        owner: owner_of.owner,
+        token_uri: nft_info.token_uri,
+        extension: nft_info.extension,
+    });
+    Ok(IbcReceiveResponse::new()
+        .add_attribute("method", "ibc_receive_resolve_name")
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .set_ack(StdAck::success(to_json_binary(&ack)?)))
+}
+
+/// Applies records sent by the voucher owner. This contract must be allowed to update the
+/// name collection, e.g. as its creator.
+fn ibc_receive_update_name_record(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    token_uri: Option<String>,
+    extension: Option<JsonValue>,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let escrow_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+    if !ESCROWED_NAMES.has(deps.storage, escrow_key) {
+        return Err(ContractError::NameNotEscrowed {
+            collection,
+            token_id,
+        });
+    }
+    let update_msg = MetadataExecuteMsg::UpdateNftInfo {
+        token_id: token_id.to_owned(),
+        token_uri,
+        extension,
+    };
+    let update_exec_msg = WasmMsg::Execute {
+        contract_addr: collection.to_owned(),
+        msg: to_json_binary(&update_msg)?,
+        funds: vec![],
+    };
+    let update_event = Event::new("my-ics-name-record-update")
+        .add_attribute("channel", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id);
+    Ok(IbcReceiveResponse::new()
+        .add_message(update_exec_msg)
+        .add_event(update_event)
+        .set_ack(StdAck::success(to_json_binary(
+            &AckSuccess::NameRecordUpdated {},
+        )?)))
+}
+
+/// Mints a new name to this contract and keeps it in escrow for the voucher minted on the other
+/// chain. This contract must be a minter of the collection.
+fn ibc_receive_register_name(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> Result<IbcReceiveResponse, ContractError> {
+    if !REGISTRABLE_COLLECTIONS.has(deps.storage, &collection) {
+        return Err(ContractError::RegistrationNotAllowed { collection });
+    }
+    let owner_of = deps
+        .querier
+        .query::<OwnerOfResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionQueryMsg::OwnerOf {
+                token_id: token_id.to_owned(),
+                include_expired: None,
+            })?,
+        }));
+    if owner_of.is_ok() {
+        return Err(ContractError::NameAlreadyTaken {
+            collection,
+            token_id,
+        });
+    }
+    validate_supply_cap(&deps, &channel_id)?;
+    rate_limit::consume_inbound(deps.storage, env.block.time, &channel_id)?;
+    // The name is owned on the other chain, the contract stands in as its sender here.
+    save_escrowed_name(
+        deps.storage,
+        (&channel_id, &collection, &token_id),
+        &Escrow {
+            sender_addr: env.contract.address.to_string(),
+        },
+    )?;
+    let mint_msg = CollectionExecuteMsg::Mint {
+        token_id: token_id.to_owned(),
+        owner: env.contract.address.to_string(),
+        token_uri: None,
+        extension: None,
+    };
+    let mint_exec_msg = WasmMsg::Execute {
+        contract_addr: collection.to_owned(),
+        msg: to_json_binary(&mint_msg)?,
+        funds: vec![],
+    };
+    let register_event = Event::new("my-ics-name-register")
+        .add_attribute("channel", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id);
+    Ok(IbcReceiveResponse::new()
+        .add_message(mint_exec_msg)
+        .add_event(register_event)
+        .set_ack(StdAck::success(to_json_binary(
+            &AckSuccess::NameRegistered {},
+        )?)))
+}
+
+/// Renews an escrowed name, paid as set in the renewal config of its collection.
+fn ibc_receive_renew_name(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> Result<IbcReceiveResponse, ContractError> {
+    if !ESCROWED_NAMES.has(deps.storage, (&channel_id, &collection, &token_id)) {
+        return Err(ContractError::NameNotEscrowed {
+            collection,
+            token_id,
+        });
+    }
+    let config = RENEWAL_CONFIGS
+        .may_load(deps.storage, &collection)?
+        .ok_or_else(|| ContractError::RenewalNotAllowed {
+            collection: collection.to_owned(),
+        })?;
+    let price = config.price;
+    match config.payment {
+        RenewalPayment::Prefunded => {
+            let available = RENEWAL_FUNDS
+                .may_load(deps.storage, &price.denom)?
+                .unwrap_or_default();
+            validate_renewal_funds(&price, available)?;
+            let remaining = available - price.amount;
+            if remaining.is_zero() {
+                RENEWAL_FUNDS.remove(deps.storage, &price.denom);
+            } else {
+                RENEWAL_FUNDS.save(deps.storage, &price.denom, &remaining)?;
+            }
+        }
+        RenewalPayment::Deposit => {
+            let deposits = RENEWAL_DEPOSITS
+                .prefix((collection.as_str(), token_id.as_str()))
+                .range(deps.storage, None, None, Order::Ascending)
+                .filter(|deposit| {
+                    deposit
+                        .as_ref()
+                        .map_or(true, |(_, deposit)| deposit.denom == price.denom)
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+            let available: Uint128 = deposits.iter().map(|(_, deposit)| deposit.amount).sum();
+            validate_renewal_funds(&price, available)?;
+            // Deposits pay in the order of their depositors, until the price is covered.
+            let mut due = price.amount;
+            for (depositor, deposit) in deposits {
+                if due.is_zero() {
+                    break;
+                }
+                let paid = deposit.amount.min(due);
+                due -= paid;
+                let key = (collection.as_str(), token_id.as_str(), depositor.as_str());
+                if paid == deposit.amount {
+                    RENEWAL_DEPOSITS.remove(deps.storage, key);
+                } else {
+                    RENEWAL_DEPOSITS.save(
+                        deps.storage,
+                        key,
+                        &Coin {
+                            denom: deposit.denom,
+                            amount: deposit.amount - paid,
+                        },
+                    )?;
+                }
+            }
+        }
+    }
+    let renew_exec_msg = WasmMsg::Execute {
+        contract_addr: collection.to_owned(),
+        msg: to_json_binary(&NameserviceExecuteMsg::Renew {
+            token_id: token_id.to_owned(),
+        })?,
+        funds: vec![price.to_owned()],
+    };
+    let renew_event = Event::new("my-ics-name-renew")
+        .add_attribute("channel", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("price", price.to_string());
+    Ok(IbcReceiveResponse::new()
+        .add_message(renew_exec_msg)
+        .add_event(renew_event)
+        .set_ack(StdAck::success(to_json_binary(
+            &AckSuccess::NameRenewed {},
+        )?)))
+}
+
+fn validate_renewal_funds(price: &Coin, available: Uint128) -> Result<(), ContractError> {
+    if available < price.amount {
+        return Err(ContractError::InsufficientRenewalFunds {
+            expected: price.to_string(),
+            available: format!("{available}{}", price.denom),
+        });
+    }
+    Ok(())
+}
+
+/// Copies the metadata of the origin name to its voucher, which this contract mints and can
+/// update.
+fn ibc_receive_sync_metadata(
+    deps: DepsMut,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    token_uri: Option<String>,
+    extension: Option<JsonValue>,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let voucher_token_id =
+        load_voucher_token_id(deps.storage, &channel_id, &collection, &token_id)?;
+    if !VOUCHER_TRACES.has(deps.storage, &voucher_token_id) {
+        return Err(ContractError::VoucherNotFound { voucher_token_id });
+    }
+    let voucher_collection = VOUCHERS_ADDR.load(deps.storage)?;
+    let update_msg = MetadataExecuteMsg::UpdateNftInfo {
+        token_id: voucher_token_id.to_owned(),
+        token_uri,
+        extension,
+    };
+    let update_exec_msg = WasmMsg::Execute {
+        contract_addr: voucher_collection,
+        msg: to_json_binary(&update_msg)?,
+        funds: vec![],
+    };
+    let sync_event = Event::new("my-ics-name-metadata-sync")
+        .add_attribute("channel", channel_id)
+        .add_attribute("original-collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute("voucher-token-id", voucher_token_id);
+    Ok(IbcReceiveResponse::new()
+        .add_message(update_exec_msg)
+        .add_event(sync_event)
+        .set_ack(StdAck::success(to_json_binary(
+            &AckSuccess::MetadataSynced {},
+        )?)))
+}
+
+/// Releases an escrowed name once the collection tells it has expired, as set by the expiry
+/// policy of the collection.
+fn ibc_receive_expire_voucher(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    collection: String,
+    token_id: String,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let escrow_key = (channel_id.as_str(), collection.as_str(), token_id.as_str());
+    let Some(escrow) = ESCROWED_NAMES.may_load(deps.storage, escrow_key)? else {
+        return Err(ContractError::NameNotEscrowed {
+            collection,
+            token_id,
+        });
+    };
+    match query_expiry(&deps, &collection, &token_id)? {
+        Some(expires_at) if expires_at <= env.block.time => {}
+        _ => {
+            return Err(ContractError::NameNotExpired {
+                collection,
+                token_id,
+            })
+        }
+    }
+    let policy = match EXPIRY_POLICIES.may_load(deps.storage, &collection)? {
+        // Names registered from the other chain have no sender to return to.
+        _ if escrow.sender_addr == env.contract.address => ExpiryPolicy::Burn,
+        policy => policy.unwrap_or(ExpiryPolicy::ReturnToSender),
+    };
+    remove_escrowed_name(deps.storage, escrow_key)?;
+    let response = match policy {
+        ExpiryPolicy::Burn => IbcReceiveResponse::new().add_message(WasmMsg::Execute {
+            contract_addr: collection.to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::Burn {
+                token_id: token_id.to_owned(),
+            })?,
+            funds: vec![],
+        }),
+        ExpiryPolicy::ReturnToSender => IbcReceiveResponse::new().add_submessage(refund_submsg(
+            deps.storage,
+            collection.to_owned(),
+            token_id.to_owned(),
+            escrow.sender_addr,
+        )?),
+    };
+    let expire_event = Event::new("my-ics-name-expire")
+        .add_attribute("channel", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id)
+        .add_attribute(
+            "policy",
+            match policy {
+                ExpiryPolicy::Burn => "burn",
+                ExpiryPolicy::ReturnToSender => "return-to-sender",
+            },
+        );
+    Ok(response
+        .add_event(expire_event)
+        .set_ack(StdAck::success(to_json_binary(
+            &AckSuccess::NameExpired {},
+        )?)))
+}
+
+fn validate_receiver(deps: &DepsMut, receiver_addr: &str) -> Result<Addr, ContractError> {
+    deps.api
+        .addr_validate(receiver_addr)
+        .map_err(|_| ContractError::InvalidReceiver {
+            receiver_addr: receiver_addr.to_owned(),
+        })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -106,37 +697,259 @@ pub fn ibc_packet_ack(
     let ack_data = from_json::<StdAck>(&ack.acknowledgement.data)
         .unwrap_or_else(|_| StdAck::Error(ack.acknowledgement.data.to_base64()));
     let original_msg = from_json::<IbcPacketMessage>(ack.original_packet.data)?;
+    let channel_id = ack.original_packet.src.channel_id;
+    let ack_event = ack_event(&channel_id, &ack_data);
+    let timeout = ack.original_packet.timeout;
+    Ok(ack_packet(deps, env, channel_id, timeout, original_msg, ack_data)?.add_event(ack_event))
+}
+
+/// Emits what the acknowledgement tells, so that clients do not have to decode it.
+fn ack_event(channel_id: &str, ack_data: &StdAck) -> Event {
+    let event = Event::new("my-ics-name-ack").add_attribute("channel", channel_id);
+    match ack_data {
+        StdAck::Success(data) => match from_json::<AckSuccess>(data) {
+            Ok(AckSuccess::VoucherMinted {
+                voucher_collection,
+                voucher_token_id,
+            }) => event
+                .add_attribute("result", "voucher-minted")
+                .add_attribute("voucher-collection", voucher_collection)
+                .add_attribute("voucher-token-id", voucher_token_id),
+            Ok(AckSuccess::NameTeleported {
+                collection,
+                token_id,
+            }) => event
+                .add_attribute("result", "name-teleported")
+                .add_attribute("collection", collection)
+                .add_attribute("token-id", token_id),
+            Ok(AckSuccess::NameUnescrowed { recipient }) => event
+                .add_attribute("result", "name-unescrowed")
+                .add_attribute("recipient", recipient),
+            Ok(AckSuccess::NameResolved(resolution)) => event
+                .add_attribute("result", "name-resolved")
+                .add_attribute("owner", resolution.owner),
+            Ok(AckSuccess::NameRecordUpdated {}) => {
+                event.add_attribute("result", "name-record-updated")
+            }
+            Ok(AckSuccess::NameRegistered {}) => event.add_attribute("result", "name-registered"),
+            Ok(AckSuccess::NameRenewed {}) => event.add_attribute("result", "name-renewed"),
+            Ok(AckSuccess::MetadataSynced {}) => event.add_attribute("result", "metadata-synced"),
+            Ok(AckSuccess::NameExpired {}) => event.add_attribute("result", "name-expired"),
+            Err(_) => event.add_attribute("result", "success"),
+        },
+        StdAck::Error(error) => {
+            append_ack_error_attributes(event.add_attribute("result", "error"), error, "message")
+        }
+    }
+}
+
+/// Adds the code and category of the error when the other side sent a typed one, and its message
+/// under `message_key`.
+fn append_ack_error_attributes(event: Event, error: &str, message_key: &str) -> Event {
+    match from_json::<AckError>(error.as_bytes()) {
+        Ok(AckError {
+            code,
+            category,
+            message,
+        }) => event
+            .add_attribute("code", code.to_string())
+            .add_attribute("category", category.as_str())
+            .add_attribute(message_key, message),
+        Err(_) => event.add_attribute(message_key, error),
+    }
+}
+
+/// Tells why the other side refused a packet whose tokens are being refunded.
+fn refund_event(channel_id: &str, collection: &str, token_id: &str, error: &str) -> Event {
+    let event = Event::new("my-ics-name-refund")
+        .add_attribute("channel", channel_id)
+        .add_attribute("collection", collection)
+        .add_attribute("token-id", token_id);
+    append_ack_error_attributes(event, error, "reason")
+}
+
+fn ack_packet(
+    deps: DepsMut,
+    env: Env,
+    channel_id: String,
+    timeout: IbcTimeout,
+    original_msg: IbcPacketMessage,
+    ack_data: StdAck,
+) -> Result<IbcBasicResponse, ContractError> {
     match original_msg {
         IbcPacketMessage::TransferName {
             collection,
             token_id,
             sender_addr,
             receiver_addr: _receiver_addr,
-        } => match ack_data {
-            StdAck::Error(_) => unescrow_name(&deps, &env, &collection, &token_id, &sender_addr),
-            StdAck::Success(_) => Ok(IbcBasicResponse::default()),
-        },
+            refund_addr,
+            expires_at: _expires_at,
+            mode: _mode,
+        } => {
+            let refund_addr = refund_addr.unwrap_or_else(|| sender_addr.to_owned());
+            let in_flight = match take_in_flight(deps.storage, &channel_id, &collection, &token_id)?
+            {
+                Some(in_flight) => in_flight,
+                None if is_legacy_packet(deps.storage, &timeout)? => {
+                    save_escrowed_name(
+                        deps.storage,
+                        (&channel_id, &collection, &token_id),
+                        &Escrow { sender_addr },
+                    )?;
+                    legacy_in_flight(EscrowedToken::Name, &refund_addr, &timeout)
+                }
+                None => return Ok(already_unescrowed(&collection, &token_id)),
+            };
+            let relayer_fee_refund = in_flight
+                .relayer_fee
+                .and_then(|paid| fees::refund_unused_relayer_fee(paid, true));
+            let response = match ack_data {
+                StdAck::Error(error) => {
+                    let fee_refund = in_flight
+                        .fee
+                        .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+                        .transpose()?;
+                    refund_name(
+                        deps,
+                        &env,
+                        &channel_id,
+                        &collection,
+                        &token_id,
+                        &refund_addr,
+                        in_flight.token,
+                    )?
+                    .add_submessages(fee_refund)
+                    .add_event(refund_event(
+                        &channel_id,
+                        &collection,
+                        &token_id,
+                        &error,
+                    ))
+                }
+                StdAck::Success(_) => {
+                    if let Some(fee) = in_flight.fee {
+                        fees::collect_fee(deps.storage, fee)?;
+                    }
+                    IbcBasicResponse::default()
+                }
+            };
+            Ok(response.add_messages(relayer_fee_refund))
+        }
         IbcPacketMessage::ReturnName {
             collection,
             token_id,
             sender_addr,
             receiver_addr: _receiver_addr,
+            refund_addr,
+        } => {
+            let refund_addr = refund_addr.unwrap_or(sender_addr);
+            let in_flight = match take_in_flight(deps.storage, &channel_id, &collection, &token_id)?
+            {
+                Some(in_flight) => in_flight,
+                None if is_legacy_packet(deps.storage, &timeout)? => {
+                    legacy_in_flight(EscrowedToken::Voucher, &refund_addr, &timeout)
+                }
+                None => return Ok(already_unescrowed(&collection, &token_id)),
+            };
+            match ack_data {
+                StdAck::Error(error) => {
+                    let fee_refund = in_flight
+                        .fee
+                        .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+                        .transpose()?;
+                    Ok(unescrow_voucher(
+                        deps,
+                        &env,
+                        &channel_id,
+                        &collection,
+                        &token_id,
+                        &refund_addr,
+                    )?
+                    .add_submessages(fee_refund)
+                    .add_event(refund_event(
+                        &channel_id,
+                        &collection,
+                        &token_id,
+                        &error,
+                    )))
+                }
+                StdAck::Success(_) => {
+                    if let Some(fee) = in_flight.fee {
+                        fees::collect_fee(deps.storage, fee)?;
+                    }
+                    burn_voucher(deps, &env, &channel_id, &collection, &token_id)
+                }
+            }
+        }
+        IbcPacketMessage::ResolveName {
+            collection,
+            token_id,
+            requester,
+        } => {
+            let result = match ack_data {
+                StdAck::Success(data) => match from_json::<AckSuccess>(&data) {
+                    Ok(AckSuccess::NameResolved(resolution)) => {
+                        ResolveNameResult::Resolved(resolution)
+                    }
+                    _ => ResolveNameResult::Failed {
+                        error: "Unexpected acknowledgement".to_owned(),
+                    },
+                },
+                StdAck::Error(error) => ResolveNameResult::Failed {
+                    error: from_json::<AckError>(error.as_bytes())
+                        .map(|ack_error| ack_error.message)
+                        .unwrap_or(error),
+                },
+            };
+            resolve_name_callback(channel_id, collection, token_id, requester, result)
+        }
+        // Nothing was escrowed for these, the ack event is enough.
+        IbcPacketMessage::UpdateNameRecord { .. }
+        | IbcPacketMessage::RenewName { .. }
+        | IbcPacketMessage::SyncMetadata { .. } => Ok(IbcBasicResponse::new()),
+        IbcPacketMessage::ExpireVoucher {
+            collection,
+            token_id,
         } => match ack_data {
-            StdAck::Error(_) => unescrow_voucher(
-                &deps,
-                &env,
-                &ack.original_packet.src.channel_id,
-                &collection,
-                &token_id,
-                &sender_addr,
-            ),
-            StdAck::Success(_) => burn_voucher(
-                &deps,
-                &env,
-                &ack.original_packet.src.channel_id,
-                &collection,
-                &token_id,
-            ),
+            StdAck::Success(_) => expire_voucher(deps, &env, &channel_id, &collection, &token_id),
+            // The name is still valid or was returned meanwhile, the voucher stays.
+            StdAck::Error(_) => Ok(IbcBasicResponse::new()),
+        },
+        IbcPacketMessage::RegisterName {
+            collection,
+            token_id,
+            owner_addr,
+        } => match ack_data {
+            StdAck::Success(_) => {
+                let voucher_collection = VOUCHERS_ADDR.load(deps.storage)?;
+                let voucher_token_id =
+                    match new_voucher_token_id(deps.storage, &channel_id, &collection, &token_id) {
+                        Ok(voucher_token_id) => voucher_token_id,
+                        // The readable id was taken since the registration was sent, the name is
+                        // registered all the same.
+                        Err(ContractError::VoucherAlreadyExists { .. }) => {
+                            compute_voucher_token_id(&channel_id, &collection, &token_id)
+                        }
+                        Err(error) => return Err(error),
+                    };
+                let (mint_exec_msg, mint_event) = mint_voucher(
+                    deps.storage,
+                    &voucher_collection,
+                    &voucher_token_id,
+                    VoucherTrace {
+                        channel_id,
+                        collection,
+                        token_id,
+                        expires_at: None,
+                    },
+                    owner_addr,
+                )?;
+                Ok(IbcBasicResponse::new()
+                    .add_message(mint_exec_msg)
+                    .add_event(mint_event))
+            }
+            // Nothing was escrowed for the registration, the ack event is enough.
+            StdAck::Error(_) => Ok(IbcBasicResponse::new()),
         },
     }
 }
@@ -147,63 +960,271 @@ pub fn ibc_packet_timeout(
     env: Env,
     msg: IbcPacketTimeoutMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
+    let channel_id = msg.packet.src.channel_id;
+    let timeout = msg.packet.timeout;
     match from_json::<IbcPacketMessage>(msg.packet.data)? {
         IbcPacketMessage::TransferName {
             collection,
             token_id,
             sender_addr,
             receiver_addr: _receiver_addr,
-        } => unescrow_name(&deps, &env, &collection, &sender_addr, &token_id),
+            refund_addr,
+            expires_at: _expires_at,
+            mode: _mode,
+        } => {
+            let refund_addr = refund_addr.unwrap_or_else(|| sender_addr.to_owned());
+            let in_flight = match take_in_flight(deps.storage, &channel_id, &collection, &token_id)?
+            {
+                Some(in_flight) => in_flight,
+                None if is_legacy_packet(deps.storage, &timeout)? => {
+                    save_escrowed_name(
+                        deps.storage,
+                        (&channel_id, &collection, &token_id),
+                        &Escrow { sender_addr },
+                    )?;
+                    legacy_in_flight(EscrowedToken::Name, &refund_addr, &timeout)
+                }
+                None => return Ok(already_unescrowed(&collection, &token_id)),
+            };
+            let fee_refund = in_flight
+                .fee
+                .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+                .transpose()?;
+            // The fee middleware refunds the whole relayer fee once the channel is closed.
+            let relayer_fee_refund = if CLOSED_CHANNELS.has(deps.storage, &channel_id) {
+                in_flight
+                    .relayer_fee
+                    .and_then(fees::refund_closed_relayer_fee)
+            } else {
+                in_flight
+                    .relayer_fee
+                    .and_then(|paid| fees::refund_unused_relayer_fee(paid, false))
+            };
+            Ok(refund_name(
+                deps,
+                &env,
+                &channel_id,
+                &collection,
+                &token_id,
+                &refund_addr,
+                in_flight.token,
+            )?
+            .add_submessages(fee_refund)
+            .add_messages(relayer_fee_refund))
+        }
         IbcPacketMessage::ReturnName {
             collection,
             token_id,
             sender_addr,
             receiver_addr: _receiver_addr,
-        } => unescrow_voucher(
-            &deps,
-            &env,
-            &msg.packet.src.channel_id,
-            &collection,
-            &token_id,
-            &sender_addr,
+            refund_addr,
+        } => {
+            let refund_addr = refund_addr.unwrap_or(sender_addr);
+            let in_flight = match take_in_flight(deps.storage, &channel_id, &collection, &token_id)?
+            {
+                Some(in_flight) => in_flight,
+                None if is_legacy_packet(deps.storage, &timeout)? => {
+                    legacy_in_flight(EscrowedToken::Voucher, &refund_addr, &timeout)
+                }
+                None => return Ok(already_unescrowed(&collection, &token_id)),
+            };
+            let fee_refund = in_flight
+                .fee
+                .map(|fee| fees::refund_fee_submsg(deps.storage, fee))
+                .transpose()?;
+            Ok(unescrow_voucher(
+                deps,
+                &env,
+                &channel_id,
+                &collection,
+                &token_id,
+                &refund_addr,
+            )?
+            .add_submessages(fee_refund))
+        }
+        IbcPacketMessage::ResolveName {
+            collection,
+            token_id,
+            requester,
+        } => resolve_name_callback(
+            channel_id,
+            collection,
+            token_id,
+            requester,
+            ResolveNameResult::Failed {
+                error: "Timed out".to_owned(),
+            },
         ),
+        IbcPacketMessage::UpdateNameRecord {
+            collection,
+            token_id,
+            ..
+        } => Ok(IbcBasicResponse::new().add_event(
+            Event::new("my-ics-name-record-update-timeout")
+                .add_attribute("channel", channel_id)
+                .add_attribute("collection", collection)
+                .add_attribute("token-id", token_id),
+        )),
+        IbcPacketMessage::RegisterName {
+            collection,
+            token_id,
+            ..
+        } => Ok(IbcBasicResponse::new().add_event(
+            Event::new("my-ics-name-register-timeout")
+                .add_attribute("channel", channel_id)
+                .add_attribute("collection", collection)
+                .add_attribute("token-id", token_id),
+        )),
+        IbcPacketMessage::RenewName {
+            collection,
+            token_id,
+            ..
+        } => Ok(IbcBasicResponse::new().add_event(
+            Event::new("my-ics-name-renew-timeout")
+                .add_attribute("channel", channel_id)
+                .add_attribute("collection", collection)
+                .add_attribute("token-id", token_id),
+        )),
+        // These can be sent again, nothing to recover.
+        IbcPacketMessage::SyncMetadata { .. } | IbcPacketMessage::ExpireVoucher { .. } => {
+            Ok(IbcBasicResponse::new())
+        }
     }
 }
 
-fn unescrow_name(
-    _deps: &DepsMut,
-    _env: &Env,
-    collection: &String,
-    token_id: &String,
-    original_sender_addr: &String,
+/// Delivers the outcome of a name resolution to the contract that asked for it.
+fn resolve_name_callback(
+    channel_id: String,
+    collection: String,
+    token_id: String,
+    requester: String,
+    result: ResolveNameResult,
 ) -> Result<IbcBasicResponse, ContractError> {
-    let unescrow_msg = CollectionExecuteMsg::TransferNft {
-        token_id: token_id.to_string(),
-        recipient: original_sender_addr.to_string(),
+    let callback_msg = ResolveNameCallbackMsg::ResolveNameCallback {
+        channel_id,
+        collection,
+        token_id,
+        result,
     };
-    let exec_msg = WasmMsg::Execute {
-        contract_addr: collection.to_string(),
-        msg: to_json_binary(&unescrow_msg)?,
+    let callback_exec_msg = WasmMsg::Execute {
+        contract_addr: requester,
+        msg: to_json_binary(&callback_msg)?,
         funds: vec![],
     };
-    Ok(IbcBasicResponse::new().add_message(exec_msg))
+    Ok(IbcBasicResponse::new()
+        .add_submessage(SubMsg::reply_on_error(callback_exec_msg, CALLBACK_REPLY_ID)))
 }
 
-fn burn_voucher(
-    deps: &DepsMut,
-    _env: &Env,
+/// Whether a packet was sent before in-flight records were kept, see `LEGACY_PACKETS_BEFORE`.
+/// Packets time out 120 seconds after being sent.
+fn is_legacy_packet(storage: &dyn Storage, timeout: &IbcTimeout) -> StdResult<bool> {
+    let Some(legacy_before) = LEGACY_PACKETS_BEFORE.may_load(storage)? else {
+        return Ok(false);
+    };
+    Ok(timeout
+        .timestamp()
+        .is_some_and(|timeout| timeout < legacy_before.plus_seconds(120)))
+}
+
+/// Stands for the in-flight record that legacy packets lack, handling them as they used to be.
+fn legacy_in_flight(token: EscrowedToken, refund_addr: &str, timeout: &IbcTimeout) -> InFlight {
+    InFlight {
+        token,
+        refund_addr: refund_addr.to_owned(),
+        sent_at: timeout
+            .timestamp()
+            .map(|timeout| timeout.minus_seconds(120))
+            .unwrap_or_default(),
+        fee: None,
+        relayer_fee: None,
+    }
+}
+
+/// Clears the in-flight record of a packet and returns it. There is none when the escrowed token
+/// has already been recovered with a forced unescrow.
+fn take_in_flight(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    collection: &str,
+    token_id: &str,
+) -> StdResult<Option<InFlight>> {
+    let in_flight_key = (channel_id, collection, token_id);
+    let in_flight = IN_FLIGHT.may_load(storage, in_flight_key)?;
+    IN_FLIGHT.remove(storage, in_flight_key);
+    Ok(in_flight)
+}
+
+fn already_unescrowed(collection: &str, token_id: &str) -> IbcBasicResponse {
+    IbcBasicResponse::new().add_event(
+        Event::new("my-ics-name-already-unescrowed")
+            .add_attribute("collection", collection)
+            .add_attribute("token-id", token_id),
+    )
+}
+
+/// Gives back a name whose transfer did not go through, from escrow or by minting it again when
+/// it was teleported.
+fn refund_name(
+    deps: DepsMut,
+    env: &Env,
     channel_id: &String,
     collection: &String,
     token_id: &String,
+    refund_addr: &String,
+    token: EscrowedToken,
 ) -> Result<IbcBasicResponse, ContractError> {
-    let vouchers: String = VOUCHERS_ADDR.load(deps.storage)?;
-    let voucher_token_id = compute_voucher_token_id(&channel_id, &collection, &token_id);
-    let burn_msg = CollectionExecuteMsg::Burn {
-        token_id: voucher_token_id,
-    };
-    let burn_exec_msg = WasmMsg::Execute {
-        contract_addr: vouchers,
-        msg: to_json_binary(&burn_msg)?,
+    match token {
+        EscrowedToken::Teleported {
+            token_uri,
+            extension,
+        } => Ok(IbcBasicResponse::new().add_submessage(remint_submsg(
+            deps.storage,
+            collection.to_owned(),
+            token_id.to_owned(),
+            refund_addr.to_owned(),
+            Remint {
+                token_uri,
+                extension,
+            },
+        )?)),
+        _ => unescrow_name(deps, env, channel_id, collection, token_id, refund_addr),
+    }
+}
+
+fn unescrow_name(
+    deps: DepsMut,
+    _env: &Env,
+    channel_id: &String,
+    collection: &String,
+    token_id: &String,
+    refund_addr: &String,
+) -> Result<IbcBasicResponse, ContractError> {
+    remove_escrowed_name(deps.storage, (channel_id, collection, token_id))?;
+    let refund_msg = refund_submsg(
+        deps.storage,
+        collection.to_owned(),
+        token_id.to_owned(),
+        refund_addr.to_owned(),
+    )?;
+    Ok(IbcBasicResponse::new().add_submessage(refund_msg))
+}
+
+fn burn_voucher(
+    deps: DepsMut,
+    _env: &Env,
+    channel_id: &String,
+    collection: &String,
+    token_id: &String,
+) -> Result<IbcBasicResponse, ContractError> {
+    let vouchers: String = VOUCHERS_ADDR.load(deps.storage)?;
+    let voucher_token_id = load_voucher_token_id(deps.storage, channel_id, collection, token_id)?;
+    remove_voucher_trace(deps.storage, &voucher_token_id)?;
+    let burn_msg = CollectionExecuteMsg::Burn {
+        token_id: voucher_token_id,
+    };
+    let burn_exec_msg = WasmMsg::Execute {
+        contract_addr: vouchers,
+        msg: to_json_binary(&burn_msg)?,
         funds: vec![],
     };
     let burn_event = Event::new("ibc-voucher-burn")
@@ -215,37 +1236,149 @@ fn burn_voucher(
         .add_event(burn_event))
 }
 
+/// Burns the voucher of an expired name when this contract holds it, e.g. unclaimed in the inbox.
+/// This contract cannot burn a voucher held by anyone else, which is left backing no name.
+fn expire_voucher(
+    deps: DepsMut,
+    env: &Env,
+    channel_id: &String,
+    collection: &String,
+    token_id: &String,
+) -> Result<IbcBasicResponse, ContractError> {
+    let vouchers = VOUCHERS_ADDR.load(deps.storage)?;
+    let voucher_token_id = load_voucher_token_id(deps.storage, channel_id, collection, token_id)?;
+    let owner_of =
+        deps.querier
+            .query::<OwnerOfResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: vouchers,
+                msg: to_json_binary(&CollectionQueryMsg::OwnerOf {
+                    token_id: voucher_token_id.to_owned(),
+                    include_expired: None,
+                })?,
+            }))?;
+    if owner_of.owner == env.contract.address {
+        return burn_voucher(deps, env, channel_id, collection, token_id);
+    }
+    remove_voucher_trace(deps.storage, &voucher_token_id)?;
+    let expire_event = Event::new("ibc-voucher-expire")
+        .add_attribute("channel", channel_id.to_owned())
+        .add_attribute("original-collection", collection.to_owned())
+        .add_attribute("token_id", token_id.to_owned())
+        .add_attribute("holder", owner_of.owner);
+    Ok(IbcBasicResponse::default().add_event(expire_event))
+}
+
 fn unescrow_voucher(
-    deps: &DepsMut,
-    _env: &Env,
+    deps: DepsMut,
+    env: &Env,
     channel_id: &String,
     collection: &String,
     token_id: &String,
-    original_sender_addr: &String,
+    refund_addr: &String,
 ) -> Result<IbcBasicResponse, ContractError> {
+    // Unclaimed vouchers returned from the inbox never left this contract.
+    if *refund_addr == env.contract.address {
+        return Ok(IbcBasicResponse::new());
+    }
     let vouchers = VOUCHERS_ADDR.load(deps.storage)?;
-    let voucher_token_id = compute_voucher_token_id(&channel_id, &collection, &token_id);
-    let unescrow_msg = CollectionExecuteMsg::TransferNft {
-        token_id: voucher_token_id,
-        recipient: original_sender_addr.to_string(),
+    let voucher_token_id = load_voucher_token_id(deps.storage, channel_id, collection, token_id)?;
+    let refund_msg = refund_submsg(
+        deps.storage,
+        vouchers,
+        voucher_token_id,
+        refund_addr.to_owned(),
+    )?;
+    Ok(IbcBasicResponse::new().add_submessage(refund_msg))
+}
+
+/// Refunds are sent as submessages so that a failing transfer, e.g. on a paused collection,
+/// does not fail the whole acknowledgement. The reply turns such a failure into a claimable refund.
+pub(crate) fn refund_submsg(
+    storage: &mut dyn Storage,
+    contract_addr: String,
+    token_id: String,
+    recipient: String,
+) -> Result<SubMsg, ContractError> {
+    let refund_msg = CollectionExecuteMsg::TransferNft {
+        token_id: token_id.to_owned(),
+        recipient: recipient.to_owned(),
     };
-    let unescrow_exec_msg = WasmMsg::Execute {
-        contract_addr: vouchers,
-        msg: to_json_binary(&unescrow_msg)?,
+    let refund_exec_msg = WasmMsg::Execute {
+        contract_addr: contract_addr.to_owned(),
+        msg: to_json_binary(&refund_msg)?,
         funds: vec![],
     };
-    Ok(IbcBasicResponse::new().add_message(unescrow_exec_msg))
+    PENDING_REFUNDS.push_back(
+        storage,
+        &Refund {
+            contract_addr,
+            token_id,
+            recipient,
+            remint: None,
+        },
+    )?;
+    Ok(SubMsg::reply_always(refund_exec_msg, REFUND_REPLY_ID))
+}
+
+/// Like `refund_submsg`, for teleported names minted again. A failing mint becomes a claimable
+/// refund, minted when claimed.
+pub(crate) fn remint_submsg(
+    storage: &mut dyn Storage,
+    contract_addr: String,
+    token_id: String,
+    recipient: String,
+    remint: Remint,
+) -> Result<SubMsg, ContractError> {
+    let remint_exec_msg = remint_teleported_msg(
+        &contract_addr,
+        &token_id,
+        &recipient,
+        remint.token_uri.to_owned(),
+        remint.extension.to_owned(),
+    )?;
+    PENDING_REFUNDS.push_back(
+        storage,
+        &Refund {
+            contract_addr,
+            token_id,
+            recipient,
+            remint: Some(remint),
+        },
+    )?;
+    Ok(SubMsg::reply_always(remint_exec_msg, REFUND_REPLY_ID))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use crate::{
-        contract::instantiate,
-        ibc::packet::ibc_packet_receive,
-        msg::{CollectionExecuteMsg, IbcPacketMessage, InstantiateMsg},
-        state::VOUCHERS_ADDR,
+        contract::{instantiate, CALLBACK_REPLY_ID, REFUND_REPLY_ID},
+        ibc::{
+            helpers::load_voucher_token_id,
+            packet::{ibc_packet_ack, ibc_packet_receive, ibc_packet_timeout},
+        },
+        msg::{
+            AckError, AckSuccess, ChannelConfig, CollectionExecuteMsg, CollectionQueryMsg,
+            ErrorCategory, ExpiryResponse, IbcPacketMessage, InstantiateMsg, JsonValue,
+            MetadataExecuteMsg, NameResolution, NameserviceExecuteMsg, RateLimit, RelayerFee,
+            RenewalConfig, RenewalPayment, ResolveNameCallbackMsg, ResolveNameResult, TransferMode,
+            VoucherIdScheme,
+        },
+        state::{
+            save_voucher_trace, Escrow, EscrowedToken, InFlight, PaidRelayerFee, PendingClaim,
+            Refund, VoucherTrace, CHANNEL_CONFIGS, CLOSED_CHANNELS, ESCROWED_NAMES, IN_FLIGHT,
+            LEGACY_PACKETS_BEFORE, PENDING_CLAIMS, PENDING_REFUNDS, REGISTRABLE_COLLECTIONS,
+            RENEWAL_CONFIGS, RENEWAL_DEPOSITS, RENEWAL_FUNDS, TELEPORT_REMOTES, VOUCHERS_ADDR,
+            VOUCHER_ID_SCHEMES, VOUCHER_TRACES,
+        },
     };
-    use cosmwasm_std::{testing, to_json_binary, Addr, Event, IbcReceiveResponse, WasmMsg};
+    use cosmwasm_std::{
+        from_json, testing, to_json_binary, to_json_string, Addr, BankMsg, Coin, ContractResult,
+        Empty, Event, IbcAcknowledgement, IbcBasicResponse, IbcReceiveResponse, IbcTimeout, StdAck,
+        SubMsg, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use cw721::msg::{NftInfoResponse, OwnerOfResponse};
 
     #[test]
     fn test_ibc_receive_transfer_name() {
@@ -270,6 +1403,9 @@ mod tests {
             token_id: "3".to_owned(),
             sender_addr: "sender".to_owned(),
             receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
         };
         let mocked_receive_packet =
             testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
@@ -300,9 +1436,17 @@ mod tests {
             .add_attribute("channel", "20".to_string())
             .add_attribute("original-collection", "original".to_string())
             .add_attribute("token-id", "3".to_string());
+        let expected_ack = AckSuccess::VoucherMinted {
+            voucher_collection: "vouchers".to_owned(),
+            voucher_token_id: "a6b4a0065ec52c2b0b296e0f7e006a85800688897737ac2a182e50d9015c998e"
+                .to_owned(),
+        };
         let expected_response = IbcReceiveResponse::default()
             .add_message(expected_mint_exec_msg)
-            .add_event(expected_event);
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&expected_ack).expect("Failed to serialize ack"),
+            ));
         assert_eq!(received_response, expected_response);
         let saved_vouchers = VOUCHERS_ADDR
             .load(&mocked_deps_mut.storage)
@@ -327,11 +1471,21 @@ mod tests {
             instantiate_msg.to_owned(),
         )
         .expect("Failed to instantiate ics name");
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
         let return_msg = IbcPacketMessage::ReturnName {
             collection: "original".to_owned(),
             token_id: "3".to_owned(),
             sender_addr: "sender".to_owned(),
             receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
         };
         let mocked_receive_packet =
             testing::mock_ibc_packet_recv("20", &return_msg).expect("Failed to mock packet");
@@ -356,8 +1510,1534 @@ mod tests {
             msg: to_json_binary(&expected_unescrow_msg).expect("Failed to serialize unescrow msg"),
             funds: vec![],
         };
-        let expected_response =
-            IbcReceiveResponse::default().add_message(expected_unescrow_exec_msg);
+        let expected_ack = AckSuccess::NameUnescrowed {
+            recipient: "receiver".to_owned(),
+        };
+        let expected_response = IbcReceiveResponse::default()
+            .add_message(expected_unescrow_exec_msg)
+            .set_ack(StdAck::success(
+                to_json_binary(&expected_ack).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_transfer_name_packet_leaves_out_defaults() {
+        // Arrange
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+
+        // Act
+        let result = to_json_string(&transfer_msg);
+
+        // Assert
+        assert_eq!(
+            result.expect("Failed to serialize packet"),
+            r#"{"transfer_name":{"collection":"original","token_id":"3","sender_addr":"sender","receiver_addr":"receiver"}}"#
+        );
+    }
+
+    #[test]
+    fn test_return_name_packet_leaves_out_defaults() {
+        // Arrange
+        let return_msg = IbcPacketMessage::ReturnName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+        };
+
+        // Act
+        let result = to_json_string(&return_msg);
+
+        // Assert
+        assert_eq!(
+            result.expect("Failed to serialize packet"),
+            r#"{"return_name":{"collection":"original","token_id":"3","sender_addr":"sender","receiver_addr":"receiver"}}"#
+        );
+    }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_invalid_receiver() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "Invalid-Receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_error = "Invalid receiver address: Invalid-Receiver".to_owned();
+        let expected_response = IbcReceiveResponse::new()
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", expected_error.to_owned())
+            .set_ack(StdAck::Error(
+                to_json_string(&AckError {
+                    code: 8,
+                    category: ErrorCategory::Permanent,
+                    message: expected_error,
+                })
+                .expect("Failed to serialize ack error"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_ibc_timeout_transfer_name_refunds_refund_addr() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "refund".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: None,
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: Some("refund".to_owned()),
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_timeout_packet =
+            testing::mock_ibc_packet_timeout("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_timeout(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_timeout_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle timeout");
+        let received_response = result.unwrap();
+        let expected_unescrow_msg = CollectionExecuteMsg::TransferNft {
+            recipient: "refund".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let expected_unescrow_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&expected_unescrow_msg).expect("Failed to serialize unescrow msg"),
+            funds: vec![],
+        };
+        let expected_response = IbcBasicResponse::new().add_submessage(SubMsg::reply_always(
+            expected_unescrow_exec_msg,
+            REFUND_REPLY_ID,
+        ));
+        assert_eq!(received_response, expected_response);
+        let pending_refund = PENDING_REFUNDS
+            .front(&mocked_deps_mut.storage)
+            .expect("Failed to load pending refund");
+        assert_eq!(
+            pending_refund,
+            Some(Refund {
+                contract_addr: "original".to_owned(),
+                token_id: "3".to_owned(),
+                recipient: "refund".to_owned(),
+                remint: None,
+            })
+        );
+        assert!(!IN_FLIGHT.has(&mocked_deps_mut.storage, ("20", "original", "3")));
+    }
+
+    #[test]
+    fn test_ibc_timeout_on_closed_channel_refunds_whole_relayer_fee() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "sender".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: Some(PaidRelayerFee {
+                        payer: "sender".to_owned(),
+                        fee: RelayerFee {
+                            recv_fee: vec![Coin::new(10, "uname")],
+                            ack_fee: vec![Coin::new(5, "uname")],
+                            timeout_fee: vec![Coin::new(7, "uname")],
+                        },
+                    }),
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        CLOSED_CHANNELS
+            .save(&mut mocked_deps_mut.storage, "20", &mocked_env.block.time)
+            .expect("Failed to close channel");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_timeout_packet =
+            testing::mock_ibc_packet_timeout("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_timeout(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_timeout_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle timeout");
+        let received_response = result.unwrap();
+        let expected_relayer_fee_refund = BankMsg::Send {
+            to_address: "sender".to_owned(),
+            amount: vec![Coin::new(22, "uname")],
+        };
+        assert_eq!(received_response.messages.len(), 2);
+        assert_eq!(
+            received_response.messages[1].msg,
+            expected_relayer_fee_refund.into()
+        );
+    }
+
+    #[test]
+    fn test_ibc_timeout_legacy_transfer_name_refunds_sender() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        LEGACY_PACKETS_BEFORE
+            .save(&mut mocked_deps_mut.storage, &mocked_env.block.time)
+            .expect("Failed to save legacy packets time");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mut mocked_timeout_packet =
+            testing::mock_ibc_packet_timeout("20", &transfer_msg).expect("Failed to mock packet");
+        // Sent 60 seconds before the upgrade.
+        mocked_timeout_packet.packet.timeout =
+            IbcTimeout::with_timestamp(mocked_env.block.time.plus_seconds(60));
+
+        // Act
+        let result = ibc_packet_timeout(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_timeout_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle timeout");
+        let received_response = result.unwrap();
+        let expected_unescrow_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: "sender".to_owned(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize unescrow msg"),
+            funds: vec![],
+        };
+        let expected_response = IbcBasicResponse::new().add_submessage(SubMsg::reply_always(
+            expected_unescrow_exec_msg,
+            REFUND_REPLY_ID,
+        ));
+        assert_eq!(received_response, expected_response);
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("20", "original", "3")));
+    }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_duplicate_voucher() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        // sha256 of "transfer_name/ibc/20/original/3"
+        let voucher_token_id = "a6b4a0065ec52c2b0b296e0f7e006a85800688897737ac2a182e50d9015c998e";
+        VOUCHER_TRACES
+            .save(
+                &mut mocked_deps_mut.storage,
+                voucher_token_id,
+                &VoucherTrace {
+                    channel_id: "20".to_owned(),
+                    collection: "original".to_owned(),
+                    token_id: "3".to_owned(),
+                    expires_at: None,
+                },
+            )
+            .expect("Failed to save voucher trace");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_error = format!("Voucher {voucher_token_id} already exists");
+        let expected_response = IbcReceiveResponse::new()
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", expected_error.to_owned())
+            .set_ack(StdAck::Error(
+                to_json_string(&AckError {
+                    code: 22,
+                    category: ErrorCategory::Permanent,
+                    message: expected_error,
+                })
+                .expect("Failed to serialize ack error"),
+            ));
         assert_eq!(received_response, expected_response);
     }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_token_id_scheme() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        VOUCHER_ID_SCHEMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original"),
+                &VoucherIdScheme::TokenId,
+            )
+            .expect("Failed to save voucher id scheme");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_ack = StdAck::success(
+            to_json_binary(&AckSuccess::VoucherMinted {
+                voucher_collection: "vouchers".to_owned(),
+                voucher_token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize ack"),
+        );
+        assert_eq!(received_response.acknowledgement, expected_ack.to_binary());
+        assert!(VOUCHER_TRACES.has(&mocked_deps_mut.storage, "3"));
+        let voucher_token_id =
+            load_voucher_token_id(&mocked_deps_mut.storage, "20", "original", "3")
+                .expect("Failed to load voucher token id");
+        assert_eq!(voucher_token_id, "3");
+    }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_token_id_scheme_collision() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        VOUCHER_ID_SCHEMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original"),
+                &VoucherIdScheme::TokenId,
+            )
+            .expect("Failed to save voucher id scheme");
+        save_voucher_trace(
+            &mut mocked_deps_mut.storage,
+            "3",
+            &VoucherTrace {
+                channel_id: "21".to_owned(),
+                collection: "original".to_owned(),
+                token_id: "3".to_owned(),
+                expires_at: None,
+            },
+        )
+        .expect("Failed to save voucher trace");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_error = "Voucher 3 already exists".to_owned();
+        let expected_response = IbcReceiveResponse::new()
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", expected_error.to_owned())
+            .set_ack(StdAck::Error(
+                to_json_string(&AckError {
+                    code: 22,
+                    category: ErrorCategory::Permanent,
+                    message: expected_error,
+                })
+                .expect("Failed to serialize ack error"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_to_inbox() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        CHANNEL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "20",
+                &ChannelConfig {
+                    inbox_claim_seconds: Some(3600),
+                    ..ChannelConfig::default()
+                },
+            )
+            .expect("Failed to save channel config");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        // sha256 of "transfer_name/ibc/20/original/3"
+        let voucher_token_id = "a6b4a0065ec52c2b0b296e0f7e006a85800688897737ac2a182e50d9015c998e";
+        let expected_mint_exec_msg = WasmMsg::Execute {
+            contract_addr: "vouchers".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::Mint {
+                token_id: voucher_token_id.to_owned(),
+                owner: mocked_env.contract.address.to_string(),
+                token_uri: None,
+                extension: None,
+            })
+            .expect("Failed to serialize mint msg"),
+            funds: vec![],
+        };
+        assert_eq!(
+            received_response.messages,
+            vec![SubMsg::new(expected_mint_exec_msg)]
+        );
+        let saved_claim = PENDING_CLAIMS
+            .load(&mocked_deps_mut.storage, voucher_token_id)
+            .expect("Failed to load pending claim");
+        assert_eq!(
+            saved_claim,
+            PendingClaim {
+                receiver_addr: "receiver".to_owned(),
+                return_addr: "sender".to_owned(),
+                claim_until: mocked_env.block.time.plus_seconds(3600),
+            }
+        );
+    }
+
+    #[test]
+    fn test_ibc_receive_transfer_name_inbound_rate_limit() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let deployer = Addr::unchecked("deployer");
+        let mocked_msg_info = testing::mock_info(deployer.as_ref(), &[]);
+        let instantiate_msg = InstantiateMsg {
+            vouchers_addr: Some("vouchers".to_owned()),
+        };
+        let _ = instantiate(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_msg_info,
+            instantiate_msg.to_owned(),
+        )
+        .expect("Failed to instantiate ics name");
+        CHANNEL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "20",
+                &ChannelConfig {
+                    rate_limit: Some(RateLimit {
+                        window_seconds: 60,
+                        max_outbound: None,
+                        max_inbound: Some(1),
+                        max_outbound_per_sender: None,
+                    }),
+                    ..ChannelConfig::default()
+                },
+            )
+            .expect("Failed to save channel config");
+        let mock_transfer_packet = |token_id: &str| {
+            let transfer_msg = IbcPacketMessage::TransferName {
+                collection: "original".to_owned(),
+                token_id: token_id.to_owned(),
+                sender_addr: "sender".to_owned(),
+                receiver_addr: "receiver".to_owned(),
+                refund_addr: None,
+                expires_at: None,
+                mode: TransferMode::Escrow,
+            };
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet")
+        };
+        let _ = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mock_transfer_packet("3"),
+        )
+        .expect("Failed to receive first packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mock_transfer_packet("4"),
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_error = "Rate limit reached for inbound transfers on channel 20".to_owned();
+        let expected_response = IbcReceiveResponse::new()
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", expected_error.to_owned())
+            .set_ack(StdAck::Error(
+                to_json_string(&AckError {
+                    code: 21,
+                    category: ErrorCategory::Retriable,
+                    message: expected_error,
+                })
+                .expect("Failed to serialize ack error"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_ibc_ack_transfer_name_error_emits_code() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        IN_FLIGHT
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &InFlight {
+                    token: EscrowedToken::Name,
+                    refund_addr: "sender".to_owned(),
+                    sent_at: mocked_env.block.time,
+                    fee: None,
+                    relayer_fee: None,
+                },
+            )
+            .expect("Failed to save in-flight packet");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Escrow,
+        };
+        let error_ack = StdAck::Error(
+            to_json_string(&AckError {
+                code: 22,
+                category: ErrorCategory::Permanent,
+                message: "Voucher already exists".to_owned(),
+            })
+            .expect("Failed to serialize ack error"),
+        );
+        let mocked_ack_packet =
+            testing::mock_ibc_packet_ack("20", &transfer_msg, IbcAcknowledgement::new(error_ack))
+                .expect("Failed to mock ack");
+
+        // Act
+        let result = ibc_packet_ack(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_ack_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle ack");
+        let received_response = result.unwrap();
+        assert_eq!(received_response.messages.len(), 1);
+        let expected_refund_event = Event::new("my-ics-name-refund")
+            .add_attribute("channel", "20")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3")
+            .add_attribute("code", "22")
+            .add_attribute("category", "permanent")
+            .add_attribute("reason", "Voucher already exists");
+        let expected_ack_event = Event::new("my-ics-name-ack")
+            .add_attribute("channel", "20")
+            .add_attribute("result", "error")
+            .add_attribute("code", "22")
+            .add_attribute("category", "retriable")
+            .add_attribute("message", "Voucher already exists");
+        assert_eq!(
+            received_response.events,
+            vec![expected_refund_event, expected_ack_event]
+        );
+    }
+
+    #[test]
+    fn test_ibc_ack_resolve_name_calls_back_requester() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let resolve_msg = IbcPacketMessage::ResolveName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            requester: "requester".to_owned(),
+        };
+        let resolution = NameResolution {
+            owner: "owner".to_owned(),
+            token_uri: Some("https://example.com/3".to_owned()),
+            extension: None,
+        };
+        let success_ack = StdAck::success(
+            to_json_binary(&AckSuccess::NameResolved(resolution.to_owned()))
+                .expect("Failed to serialize ack"),
+        );
+        let mocked_ack_packet =
+            testing::mock_ibc_packet_ack("20", &resolve_msg, IbcAcknowledgement::new(success_ack))
+                .expect("Failed to mock ack");
+
+        // Act
+        let result = ibc_packet_ack(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_ack_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle ack");
+        let received_response = result.unwrap();
+        let expected_callback_msg = WasmMsg::Execute {
+            contract_addr: "requester".to_owned(),
+            msg: to_json_binary(&ResolveNameCallbackMsg::ResolveNameCallback {
+                channel_id: "20".to_owned(),
+                collection: "original".to_owned(),
+                token_id: "3".to_owned(),
+                result: ResolveNameResult::Resolved(resolution),
+            })
+            .expect("Failed to serialize callback"),
+            funds: vec![],
+        };
+        assert_eq!(
+            received_response.messages,
+            vec![SubMsg::reply_on_error(
+                expected_callback_msg,
+                CALLBACK_REPLY_ID
+            )]
+        );
+    }
+
+    #[test]
+    fn test_ibc_receive_resolve_name_keeps_extension() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let extension = JsonValue::Object(BTreeMap::from([(
+            "records".to_owned(),
+            JsonValue::Array(vec![JsonValue::String("address".to_owned())]),
+        )]));
+        let nft_info = NftInfoResponse {
+            token_uri: None,
+            extension: Some(extension.to_owned()),
+        };
+        mocked_deps_mut
+            .querier
+            .update_wasm(move |query| match query {
+                WasmQuery::Smart { msg, .. } => {
+                    let response = match from_json::<CollectionQueryMsg>(msg)
+                        .expect("Failed to parse query")
+                    {
+                        CollectionQueryMsg::OwnerOf { .. } => to_json_binary(&OwnerOfResponse {
+                            owner: "owner".to_owned(),
+                            approvals: vec![],
+                        }),
+                        _ => to_json_binary(&nft_info),
+                    };
+                    SystemResult::Ok(ContractResult::Ok(
+                        response.expect("Failed to serialize query response"),
+                    ))
+                }
+                _ => panic!("Unexpected query"),
+            });
+        let resolve_msg = IbcPacketMessage::ResolveName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            requester: "requester".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &resolve_msg).expect("Failed to mock packet");
+
+        // Act
+        let result =
+            ibc_packet_receive(mocked_deps_mut.as_mut(), mocked_env, mocked_receive_packet);
+
+        // Assert
+        assert!(result.is_ok(), "Failed to resolve name");
+        let expected_ack = StdAck::success(
+            to_json_binary(&AckSuccess::NameResolved(NameResolution {
+                owner: "owner".to_owned(),
+                token_uri: None,
+                extension: Some(extension),
+            }))
+            .expect("Failed to serialize ack"),
+        );
+        assert_eq!(result.unwrap().acknowledgement, expected_ack.to_binary());
+    }
+
+    #[test]
+    fn test_ibc_receive_update_name_record() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        let extension = JsonValue::Object(BTreeMap::from([(
+            "records".to_owned(),
+            JsonValue::Array(vec![JsonValue::String("dns=example.com".to_owned())]),
+        )]));
+        let update_msg = IbcPacketMessage::UpdateNameRecord {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "voucher-owner".to_owned(),
+            token_uri: Some("https://example.com/3".to_owned()),
+            extension: Some(extension.to_owned()),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &update_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_update_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&MetadataExecuteMsg::UpdateNftInfo {
+                token_id: "3".to_owned(),
+                token_uri: Some("https://example.com/3".to_owned()),
+                extension: Some(extension),
+            })
+            .expect("Failed to serialize update msg"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-record-update")
+            .add_attribute("channel", "20")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3");
+        let expected_response = IbcReceiveResponse::new()
+            .add_message(expected_update_exec_msg)
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&AckSuccess::NameRecordUpdated {}).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_ibc_receive_register_name() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        REGISTRABLE_COLLECTIONS
+            .save(&mut mocked_deps_mut.storage, "original", &Empty {})
+            .expect("Failed to save registrable collection");
+        let register_msg = IbcPacketMessage::RegisterName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            owner_addr: "owner".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &register_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_mint_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::Mint {
+                token_id: "3".to_owned(),
+                owner: mocked_env.contract.address.to_string(),
+                token_uri: None,
+                extension: None,
+            })
+            .expect("Failed to serialize mint msg"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-register")
+            .add_attribute("channel", "20")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3");
+        let expected_response = IbcReceiveResponse::new()
+            .add_message(expected_mint_exec_msg)
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&AckSuccess::NameRegistered {}).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+        let escrow = ESCROWED_NAMES
+            .load(&mocked_deps_mut.storage, ("20", "original", "3"))
+            .expect("Failed to load escrow");
+        assert_eq!(escrow.sender_addr, mocked_env.contract.address.to_string());
+    }
+
+    #[test]
+    fn test_ibc_receive_register_name_taken() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        REGISTRABLE_COLLECTIONS
+            .save(&mut mocked_deps_mut.storage, "original", &Empty {})
+            .expect("Failed to save registrable collection");
+        mocked_deps_mut.querier.update_wasm(|_| {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&OwnerOfResponse {
+                    owner: "someone".to_owned(),
+                    approvals: vec![],
+                })
+                .expect("Failed to serialize owner response"),
+            ))
+        });
+        let register_msg = IbcPacketMessage::RegisterName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            owner_addr: "owner".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &register_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_ack = StdAck::Error(
+            to_json_string(&AckError {
+                code: 30,
+                category: ErrorCategory::Permanent,
+                message: "Token 3 of original is already taken".to_owned(),
+            })
+            .expect("Failed to serialize ack error"),
+        );
+        assert_eq!(received_response.acknowledgement, expected_ack.to_binary());
+        assert!(received_response.messages.is_empty());
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("20", "original", "3")));
+    }
+
+    #[test]
+    fn test_ibc_receive_renew_name_from_deposit() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        RENEWAL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "original",
+                &RenewalConfig {
+                    price: Coin::new(60, "uname"),
+                    payment: RenewalPayment::Deposit,
+                },
+            )
+            .expect("Failed to save renewal config");
+        RENEWAL_DEPOSITS
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("original", "3", "depositor"),
+                &Coin::new(40, "uname"),
+            )
+            .expect("Failed to save renewal deposit");
+        RENEWAL_DEPOSITS
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("original", "3", "other-depositor"),
+                &Coin::new(60, "uname"),
+            )
+            .expect("Failed to save renewal deposit");
+        let renew_msg = IbcPacketMessage::RenewName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "voucher-owner".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &renew_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_renew_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&NameserviceExecuteMsg::Renew {
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize renew msg"),
+            funds: vec![Coin::new(60, "uname")],
+        };
+        let expected_event = Event::new("my-ics-name-renew")
+            .add_attribute("channel", "20")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3")
+            .add_attribute("price", "60uname");
+        let expected_response = IbcReceiveResponse::new()
+            .add_message(expected_renew_exec_msg)
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&AckSuccess::NameRenewed {}).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+        assert!(!RENEWAL_DEPOSITS.has(&mocked_deps_mut.storage, ("original", "3", "depositor")));
+        let remaining = RENEWAL_DEPOSITS
+            .load(
+                &mocked_deps_mut.storage,
+                ("original", "3", "other-depositor"),
+            )
+            .expect("Failed to load renewal deposit");
+        assert_eq!(remaining, Coin::new(40, "uname"));
+    }
+
+    #[test]
+    fn test_ibc_receive_renew_name_prefunded_ignores_balance() {
+        // Arrange
+        let mut mocked_deps_mut =
+            testing::mock_dependencies_with_balance(&[Coin::new(1000, "uname")]);
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        RENEWAL_CONFIGS
+            .save(
+                &mut mocked_deps_mut.storage,
+                "original",
+                &RenewalConfig {
+                    price: Coin::new(60, "uname"),
+                    payment: RenewalPayment::Prefunded,
+                },
+            )
+            .expect("Failed to save renewal config");
+        let renew_msg = IbcPacketMessage::RenewName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "voucher-owner".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &renew_msg).expect("Failed to mock packet");
+        let unfunded = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet.to_owned(),
+        )
+        .expect("Failed to receive packet");
+        RENEWAL_FUNDS
+            .save(&mut mocked_deps_mut.storage, "uname", &Uint128::new(100))
+            .expect("Failed to save renewal funds");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        let expected_unfunded_ack = StdAck::Error(
+            to_json_string(&AckError {
+                code: 33,
+                category: ErrorCategory::Retriable,
+                message: "Renewal costs 60uname, only 0uname available".to_owned(),
+            })
+            .expect("Failed to serialize ack error"),
+        );
+        assert_eq!(unfunded.acknowledgement, expected_unfunded_ack.to_binary());
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        assert_eq!(
+            received_response.acknowledgement,
+            StdAck::success(
+                to_json_binary(&AckSuccess::NameRenewed {}).expect("Failed to serialize ack")
+            )
+            .to_binary()
+        );
+        let remaining = RENEWAL_FUNDS
+            .load(&mocked_deps_mut.storage, "uname")
+            .expect("Failed to load renewal funds");
+        assert_eq!(remaining, Uint128::new(40));
+    }
+
+    #[test]
+    fn test_ibc_receive_sync_metadata() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        let voucher_token_id = "a6b4a0065ec52c2b0b296e0f7e006a85800688897737ac2a182e50d9015c998e";
+        VOUCHERS_ADDR
+            .save(&mut mocked_deps_mut.storage, &"vouchers".to_owned())
+            .expect("Failed to save vouchers address");
+        VOUCHER_TRACES
+            .save(
+                &mut mocked_deps_mut.storage,
+                voucher_token_id,
+                &VoucherTrace {
+                    channel_id: "20".to_owned(),
+                    collection: "original".to_owned(),
+                    token_id: "3".to_owned(),
+                    expires_at: None,
+                },
+            )
+            .expect("Failed to save voucher trace");
+        let extension = JsonValue::Object(BTreeMap::from([(
+            "description".to_owned(),
+            JsonValue::String("Name 3".to_owned()),
+        )]));
+        let sync_msg = IbcPacketMessage::SyncMetadata {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            token_uri: Some("https://example.com/3".to_owned()),
+            extension: Some(extension.to_owned()),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &sync_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_update_exec_msg = WasmMsg::Execute {
+            contract_addr: "vouchers".to_owned(),
+            msg: to_json_binary(&MetadataExecuteMsg::UpdateNftInfo {
+                token_id: voucher_token_id.to_owned(),
+                token_uri: Some("https://example.com/3".to_owned()),
+                extension: Some(extension),
+            })
+            .expect("Failed to serialize update msg"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-metadata-sync")
+            .add_attribute("channel", "20")
+            .add_attribute("original-collection", "original")
+            .add_attribute("token-id", "3")
+            .add_attribute("voucher-token-id", voucher_token_id);
+        let expected_response = IbcReceiveResponse::new()
+            .add_message(expected_update_exec_msg)
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&AckSuccess::MetadataSynced {}).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+    }
+
+    #[test]
+    fn test_ibc_receive_expire_voucher_returns_name() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: "sender".to_owned(),
+                },
+            )
+            .expect("Failed to save escrow");
+        let expires_at = mocked_env.block.time.minus_seconds(1);
+        mocked_deps_mut.querier.update_wasm(move |_| {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&ExpiryResponse {
+                    expires_at: Some(expires_at),
+                })
+                .expect("Failed to serialize expiry response"),
+            ))
+        });
+        let expire_msg = IbcPacketMessage::ExpireVoucher {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &expire_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_refund_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::TransferNft {
+                recipient: "sender".to_owned(),
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-expire")
+            .add_attribute("channel", "20")
+            .add_attribute("collection", "original")
+            .add_attribute("token-id", "3")
+            .add_attribute("policy", "return-to-sender");
+        let expected_response = IbcReceiveResponse::new()
+            .add_submessage(SubMsg::reply_always(
+                expected_refund_exec_msg,
+                REFUND_REPLY_ID,
+            ))
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&AckSuccess::NameExpired {}).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("20", "original", "3")));
+    }
+
+    #[test]
+    fn test_ibc_receive_expire_voucher_burns_registered_name() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        ESCROWED_NAMES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original", "3"),
+                &Escrow {
+                    sender_addr: mocked_env.contract.address.to_string(),
+                },
+            )
+            .expect("Failed to save escrow");
+        let expires_at = mocked_env.block.time.minus_seconds(1);
+        mocked_deps_mut.querier.update_wasm(move |_| {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&ExpiryResponse {
+                    expires_at: Some(expires_at),
+                })
+                .expect("Failed to serialize expiry response"),
+            ))
+        });
+        let expire_msg = IbcPacketMessage::ExpireVoucher {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &expire_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_burn_exec_msg = WasmMsg::Execute {
+            contract_addr: "original".to_owned(),
+            msg: to_json_binary(&CollectionExecuteMsg::Burn {
+                token_id: "3".to_owned(),
+            })
+            .expect("Failed to serialize collection message"),
+            funds: vec![],
+        };
+        assert_eq!(received_response.messages.len(), 1);
+        assert_eq!(
+            received_response.messages[0].msg,
+            expected_burn_exec_msg.into()
+        );
+        assert!(!ESCROWED_NAMES.has(&mocked_deps_mut.storage, ("20", "original", "3")));
+    }
+
+    #[test]
+    fn test_ibc_ack_expire_voucher_keeps_holder_voucher() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        VOUCHERS_ADDR
+            .save(&mut mocked_deps_mut.storage, &"vouchers".to_owned())
+            .expect("Failed to save vouchers address");
+        save_voucher_trace(
+            &mut mocked_deps_mut.storage,
+            "voucher-3",
+            &VoucherTrace {
+                channel_id: "20".to_owned(),
+                collection: "original".to_owned(),
+                token_id: "3".to_owned(),
+                expires_at: Some(mocked_env.block.time.minus_seconds(1)),
+            },
+        )
+        .expect("Failed to save voucher trace");
+        mocked_deps_mut.querier.update_wasm(|_| {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&OwnerOfResponse {
+                    owner: "holder".to_owned(),
+                    approvals: vec![],
+                })
+                .expect("Failed to serialize owner response"),
+            ))
+        });
+        let expire_msg = IbcPacketMessage::ExpireVoucher {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let success_ack = StdAck::success(
+            to_json_binary(&AckSuccess::NameExpired {}).expect("Failed to serialize ack"),
+        );
+        let mocked_ack_packet =
+            testing::mock_ibc_packet_ack("20", &expire_msg, IbcAcknowledgement::new(success_ack))
+                .expect("Failed to mock ack");
+
+        // Act
+        let result = ibc_packet_ack(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_ack_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to handle ack");
+        let received_response = result.unwrap();
+        assert!(received_response.messages.is_empty());
+        assert!(!VOUCHER_TRACES.has(&mocked_deps_mut.storage, "voucher-3"));
+        let voucher_token_id =
+            load_voucher_token_id(&mocked_deps_mut.storage, "20", "original", "3")
+                .expect("Failed to load voucher token id");
+        assert_ne!(voucher_token_id, "voucher-3");
+    }
+
+    #[test]
+    fn test_ibc_receive_teleport_name() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        TELEPORT_REMOTES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("20", "original"),
+                &"local-names".to_owned(),
+            )
+            .expect("Failed to save teleport collection");
+        let extension = JsonValue::Object(BTreeMap::from([(
+            "name".to_owned(),
+            JsonValue::String("three".to_owned()),
+        )]));
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Teleport {
+                token_uri: Some("https://example.com/3".to_owned()),
+                extension: Some(extension.to_owned()),
+            },
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        let expected_mint_exec_msg = WasmMsg::Execute {
+            contract_addr: "local-names".to_owned(),
+            msg: to_json_binary(&MetadataExecuteMsg::Mint {
+                token_id: "3".to_owned(),
+                owner: "receiver".to_owned(),
+                token_uri: Some("https://example.com/3".to_owned()),
+                extension: Some(extension),
+            })
+            .expect("Failed to serialize mint msg"),
+            funds: vec![],
+        };
+        let expected_event = Event::new("my-ics-name-teleport")
+            .add_attribute("channel", "20")
+            .add_attribute("remote-collection", "original")
+            .add_attribute("collection", "local-names")
+            .add_attribute("token-id", "3");
+        let expected_ack = AckSuccess::NameTeleported {
+            collection: "local-names".to_owned(),
+            token_id: "3".to_owned(),
+        };
+        let expected_response = IbcReceiveResponse::new()
+            .add_message(expected_mint_exec_msg)
+            .add_event(expected_event)
+            .set_ack(StdAck::success(
+                to_json_binary(&expected_ack).expect("Failed to serialize ack"),
+            ));
+        assert_eq!(received_response, expected_response);
+        assert!(!VOUCHER_TRACES.has(
+            &mocked_deps_mut.storage,
+            "a6b4a0065ec52c2b0b296e0f7e006a85800688897737ac2a182e50d9015c998e"
+        ));
+    }
+
+    #[test]
+    fn test_ibc_receive_teleport_name_other_channel() {
+        // Arrange
+        let mut mocked_deps_mut = testing::mock_dependencies();
+        let mocked_env = testing::mock_env();
+        TELEPORT_REMOTES
+            .save(
+                &mut mocked_deps_mut.storage,
+                ("21", "original"),
+                &"local-names".to_owned(),
+            )
+            .expect("Failed to save teleport collection");
+        let transfer_msg = IbcPacketMessage::TransferName {
+            collection: "original".to_owned(),
+            token_id: "3".to_owned(),
+            sender_addr: "sender".to_owned(),
+            receiver_addr: "receiver".to_owned(),
+            refund_addr: None,
+            expires_at: None,
+            mode: TransferMode::Teleport {
+                token_uri: None,
+                extension: None,
+            },
+        };
+        let mocked_receive_packet =
+            testing::mock_ibc_packet_recv("20", &transfer_msg).expect("Failed to mock packet");
+
+        // Act
+        let result = ibc_packet_receive(
+            mocked_deps_mut.as_mut(),
+            mocked_env.to_owned(),
+            mocked_receive_packet,
+        );
+
+        // Assert
+        assert!(result.is_ok(), "Failed to receive packet");
+        let received_response = result.unwrap();
+        assert!(received_response.messages.is_empty());
+        let expected_error = "Names of original cannot be teleported here".to_owned();
+        assert_eq!(
+            received_response.acknowledgement,
+            StdAck::Error(
+                to_json_string(&AckError {
+                    code: 40,
+                    category: ErrorCategory::Permanent,
+                    message: expected_error,
+                })
+                .expect("Failed to serialize ack error"),
+            )
+            .to_binary()
+        );
+    }
 }
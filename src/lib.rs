@@ -1,5 +1,7 @@
 pub mod contract;
 mod error;
+mod fees;
 pub mod ibc;
 pub mod msg;
+mod rate_limit;
 mod state;
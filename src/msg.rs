@@ -1,15 +1,70 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Empty;
+use cosmwasm_std::{Coin, Empty, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
 use cw721::msg::{Cw721ExecuteMsg, Cw721QueryMsg};
 
 pub type CollectionExecuteMsg = Cw721ExecuteMsg<Option<Empty>, Option<Empty>, Empty>;
 pub type CollectionQueryMsg = Cw721QueryMsg<Option<Empty>, Option<Empty>, Empty>;
+/// Collection messages carrying the metadata of a name as is, whatever its extension type.
+pub type MetadataExecuteMsg = Cw721ExecuteMsg<Option<JsonValue>, Option<Empty>, Empty>;
+
+/// Messages of the nameservice collection that are not part of cw721.
+#[cw_serde]
+pub enum NameserviceExecuteMsg {
+    /// Extends the registration of a name, paid with the funds sent.
+    Renew { token_id: String },
+}
+
+/// Queries of the nameservice collection that are not part of cw721.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum NameserviceQueryMsg {
+    #[returns(ExpiryResponse)]
+    Expiry { token_id: String },
+}
+
+#[cw_serde]
+pub struct ExpiryResponse {
+    /// `None` when the name never expires.
+    pub expires_at: Option<Timestamp>,
+}
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub vouchers_addr: Option<String>,
 }
 
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Names escrowed by a version that did not record escrows, so that they can be returned.
+    #[serde(default)]
+    pub escrowed_names: Vec<LegacyEscrow>,
+    /// Whether the previous version kept no in-flight records. Its packets are then refunded on
+    /// failure as they used to be.
+    #[serde(default)]
+    pub legacy_in_flight: bool,
+    /// Vouchers minted by a version that did not trace them, so that they can be returned.
+    #[serde(default)]
+    pub vouchers: Vec<LegacyVoucher>,
+}
+
+#[cw_serde]
+pub struct LegacyEscrow {
+    pub channel_id: String,
+    pub collection: String,
+    pub token_id: String,
+    pub sender_addr: String,
+}
+
+#[cw_serde]
+pub struct LegacyVoucher {
+    pub channel_id: String,
+    pub collection: String,
+    pub token_id: String,
+}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     IbcTransferName {
@@ -17,22 +72,350 @@ pub enum ExecuteMsg {
         collection: String,
         token_id: String,
         receiver_addr: String,
+        refund_addr: Option<String>,
+        /// Fee paid to relayers through the ICS-29 fee middleware, on top of the bridge fee.
+        relayer_fee: Option<RelayerFee>,
+    },
+    IbcReturnName {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        receiver_addr: String,
+        refund_addr: Option<String>,
+    },
+    /// Claims a refund that could not be delivered automatically. `collection` is the contract
+    /// holding the escrowed token, i.e. the name collection or the vouchers collection. Teleported
+    /// names are minted again in their collection.
+    ClaimRefund {
+        collection: String,
+        token_id: String,
+        recipient: Option<String>,
+    },
+    /// Sends the collected fees to the treasury. Anyone may trigger it.
+    WithdrawFees {},
+    /// Claims the fee refunds of the sender that could not be delivered automatically.
+    ClaimFees { recipient: Option<String> },
+    /// Receives a fee in an allowlisted cw20, with a `Cw20HookMsg` as message.
+    Receive(Cw20ReceiveMsg),
+    /// Resolves a name of the origin chain. The sender receives a `ResolveNameCallbackMsg`.
+    ResolveName {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Updates the records of an escrowed name from the voucher chain. Only the voucher owner or
+    /// an approved operator can do it.
+    UpdateNameRecord {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        token_uri: Option<String>,
+        extension: Option<JsonValue>,
+    },
+    /// Registers a new name on the origin chain, which keeps it in escrow. Once registered, a
+    /// voucher is minted here to `owner_addr`, or to the sender.
+    RegisterName {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        owner_addr: Option<String>,
+    },
+    /// Renews an escrowed name from the voucher chain. Only the voucher owner or an approved
+    /// operator can do it.
+    RenewName {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Adds the funds sent to the deposit paying for the renewals of a name, for collections
+    /// renewed with `RenewalPayment::Deposit`. Typically called by an ICS-20 transfer hook.
+    /// Anyone can deposit, as the ICS-20 transfer that may have carried the funds is not checked.
+    /// Renewals are paid from all the deposits made for the name in the denom of the price.
+    DepositRenewal {
+        collection: String,
+        token_id: String,
+    },
+    /// Withdraws what is left of the renewal deposit of the sender for a name, in the denom it was
+    /// made in.
+    WithdrawRenewalDeposit {
+        collection: String,
+        token_id: String,
+    },
+    /// Adds the funds sent to those paying for renewals of collections renewed with
+    /// `RenewalPayment::Prefunded`. Funds added cannot be withdrawn.
+    FundRenewals {},
+    /// Sends the current metadata of an escrowed name, extension included, to its voucher. Anyone
+    /// can do it, as only what the collection holds is sent.
+    SyncMetadata {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Asks the origin chain to release a name past its expiry, and burns its voucher once done
+    /// when this contract holds it. A voucher held by anyone else no longer backs any name. Anyone
+    /// can do it.
+    ExpireVoucher {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Takes a voucher held in the inbox of the channel. Only its receiver can do it, within the
+    /// claim window.
+    ClaimVoucher {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Sends a voucher left unclaimed past its claim window back to the origin chain, where the
+    /// name is released to its sender. This is a permissionless crank: anyone can do it, nothing
+    /// is charged, and the name only ever goes back to its sender.
+    ReturnUnclaimed {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+}
+
+/// Receive, ack and timeout fees escrowed by the ICS-29 fee middleware, all sent as funds.
+#[cw_serde]
+pub struct RelayerFee {
+    pub recv_fee: Vec<Coin>,
+    pub ack_fee: Vec<Coin>,
+    pub timeout_fee: Vec<Coin>,
+}
+
+/// Transfers and returns paid with cw20 tokens, sent as `Cw20ReceiveMsg::msg`.
+#[cw_serde]
+pub enum Cw20HookMsg {
+    IbcTransferName {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        receiver_addr: String,
+        refund_addr: Option<String>,
     },
     IbcReturnName {
         channel_id: String,
         collection: String,
         token_id: String,
         receiver_addr: String,
+        refund_addr: Option<String>,
     },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
-pub enum QueryMsg {}
+pub enum QueryMsg {
+    #[returns(SupplyResponse)]
+    Supply {
+        channel_id: String,
+        collection: String,
+    },
+    #[returns(VoucherResponse)]
+    Voucher { voucher_token_id: String },
+    #[returns(PendingClaimResponse)]
+    PendingClaim { voucher_token_id: String },
+}
+
+#[cw_serde]
+pub struct SupplyResponse {
+    /// Names of `collection` escrowed here and sent over the channel.
+    pub escrowed: u64,
+    /// Vouchers minted here for names of the remote `collection` received over the channel.
+    pub vouchers: u64,
+}
+
+#[cw_serde]
+pub struct VoucherResponse {
+    pub channel_id: String,
+    /// Collection of the name on the origin chain.
+    pub collection: String,
+    pub token_id: String,
+    /// Expiry of the name on the origin chain when it was transferred. Renewals made since are
+    /// not reflected, the origin chain checks the actual expiry.
+    pub expires_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct PendingClaimResponse {
+    pub receiver_addr: String,
+    /// End of the claim window, after which the voucher can be returned to the origin chain.
+    pub claim_until: Timestamp,
+}
 
 #[cw_serde]
 pub enum SudoMsg {
     UpdateVouchersAddr(Option<String>),
+    UpdateChannelConfig {
+        channel_id: String,
+        config: ChannelConfig,
+    },
+    ClaimRefund {
+        collection: String,
+        token_id: String,
+        recipient: Option<String>,
+    },
+    /// Returns an escrowed name, or a voucher escrowed for return, to `recipient`. Only permitted
+    /// once the channel is closed, as packets in flight on an open channel may still be delivered.
+    ForceUnescrow {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        recipient: String,
+    },
+    /// Refunds the packets still in flight on a closed channel, at most `limit` at a time, and,
+    /// depending on `policy`, makes the names escrowed behind it claimable by their senders.
+    ///
+    /// WARNING: this contract cannot see the vouchers minted on the other chain for the names
+    /// escrowed here. Settling with `ReclaimEscrowed` while those vouchers still circulate is a
+    /// double spend, so only do it once the other chain has burnt or frozen them.
+    SettleChannel {
+        channel_id: String,
+        policy: SettlementPolicy,
+        limit: Option<u32>,
+    },
+    /// Closes a channel on which no packets are in flight.
+    CloseChannel {
+        channel_id: String,
+    },
+    /// Adds or removes a counterparty allowed to open channels. Once at least one is trusted,
+    /// channels from other counterparties are rejected.
+    UpdateTrustedCounterparty {
+        connection_id: String,
+        port_id: String,
+        trusted: bool,
+    },
+    /// Sets whether any counterparty may open channels while none is trusted. Off by default, so
+    /// that no channel can be opened until a counterparty is trusted or this is turned on.
+    UpdateAnyCounterpartyAllowed(bool),
+    /// Sets whether a counterparty may only have one open channel with this contract.
+    UpdateOneChannelPerCounterparty(bool),
+    /// Sets, or clears with `None`, the fee due when neither the collection nor the channel sets one.
+    UpdateFlatFee(Option<Coin>),
+    /// Sets, or clears with `None`, the fee due for transfers and returns of `collection`.
+    UpdateCollectionFee {
+        collection: String,
+        fee: Option<Coin>,
+    },
+    UpdateTreasury(String),
+    /// Allows, or disallows with `None`, a cw20 token as fee, with the amount due.
+    UpdateCw20Fee {
+        cw20_addr: String,
+        fee: Option<Uint128>,
+    },
+    /// Sets whether names of `collection` may be registered from other chains. This contract
+    /// must be a minter of the collection.
+    UpdateRegistrableCollection {
+        collection: String,
+        registrable: bool,
+    },
+    /// Allows, or disallows with `None`, renewals of names of `collection` from other chains.
+    UpdateRenewalConfig {
+        collection: String,
+        config: Option<RenewalConfig>,
+    },
+    /// Sends the current metadata of an escrowed name, extension included, to its voucher, like
+    /// `ExecuteMsg::SyncMetadata`.
+    SyncMetadata {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+    },
+    /// Sets, or resets with `None`, what happens to expired names of `collection` once their
+    /// voucher is expired. Defaults to `ExpiryPolicy::ReturnToSender`.
+    UpdateExpiryPolicy {
+        collection: String,
+        policy: Option<ExpiryPolicy>,
+    },
+    /// Teleports names of `collection` over `channel_id` to `remote_collection` on the chain at
+    /// the other end, and back, or stops with `None`. This contract must be a minter of both.
+    UpdateTeleportCollection {
+        channel_id: String,
+        collection: String,
+        remote_collection: Option<String>,
+    },
+    /// Sets, or resets with `None`, the form of the ids of vouchers minted for names of
+    /// `collection` received over `channel_id`. Defaults to `VoucherIdScheme::Hash`. Vouchers
+    /// already minted keep their id.
+    UpdateVoucherIdScheme {
+        channel_id: String,
+        collection: String,
+        scheme: Option<VoucherIdScheme>,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum VoucherIdScheme {
+    /// Hex encoded SHA-256 of the channel, collection and token id, unique by construction.
+    #[default]
+    Hash,
+    /// The token id of the name, e.g. `alice`.
+    TokenId,
+    /// The channel and token id of the name, e.g. `channel-0/alice`.
+    ChannelTokenId,
+}
+
+#[cw_serde]
+pub enum ExpiryPolicy {
+    /// Burn the name, leaving it to the collection to make it available again.
+    Burn,
+    /// Send the name back to the sender who escrowed it. Names registered from the other chain,
+    /// which have no sender here, are burnt.
+    ReturnToSender,
+}
+
+#[cw_serde]
+pub struct RenewalConfig {
+    /// Funds sent with `NameserviceExecuteMsg::Renew`.
+    pub price: Coin,
+    pub payment: RenewalPayment,
+}
+
+#[cw_serde]
+pub enum RenewalPayment {
+    /// Paid from the funds added beforehand with `ExecuteMsg::FundRenewals`. The rest of the
+    /// balance of this contract, e.g. fees and deposits, is never used.
+    Prefunded,
+    /// Paid from the deposit made for the name, see `ExecuteMsg::DepositRenewal`.
+    Deposit,
+}
+
+#[cw_serde]
+pub enum SettlementPolicy {
+    /// Only refund the packets still in flight.
+    RefundInFlight,
+    /// Also let the original senders claim back the names escrowed behind the channel, and burn
+    /// the names registered from the other chain, which have no sender here. Only safe once the
+    /// other chain has burnt or frozen the vouchers it minted for these names, which this
+    /// contract cannot check.
+    ReclaimEscrowed,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ChannelConfig {
+    /// Bech32 prefix expected of receiver addresses on the other side of the channel.
+    pub bech32_prefix: Option<String>,
+    /// Maximum number of names escrowed behind the channel, all collections included.
+    pub supply_cap: Option<u64>,
+    /// Limits on the number of names moved over the channel per time window.
+    pub rate_limit: Option<RateLimit>,
+    /// Fee due for transfers and returns over the channel, unless the collection sets one.
+    pub fee: Option<Coin>,
+    /// Seconds receivers have to claim the vouchers minted for names received over the channel,
+    /// which this contract holds meanwhile. When not set, vouchers are minted to receivers.
+    pub inbox_claim_seconds: Option<u64>,
+}
+
+/// Maximum numbers of names moved over a channel in a fixed time window. Outbound covers
+/// transfers and returns sent from here, inbound covers those received.
+#[cw_serde]
+pub struct RateLimit {
+    pub window_seconds: u64,
+    pub max_outbound: Option<u64>,
+    pub max_inbound: Option<u64>,
+    pub max_outbound_per_sender: Option<u64>,
 }
 
 // #[derive(Debug)]
@@ -43,11 +426,178 @@ pub enum IbcPacketMessage {
         token_id: String,
         sender_addr: String,
         receiver_addr: String,
+        /// Where to send the name or voucher back when the packet fails. Defaults to `sender_addr`.
+        /// Left out when `None`, counterparties without it refuse packets carrying it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        refund_addr: Option<String>,
+        /// Expiry of the name on the origin chain, if the collection tells one.
+        ///
+        /// Left out when `None`, and `mode` when `TransferMode::Escrow`, so that plain transfers
+        /// keep the shape counterparties without these fields accept. Upgrade the receiving
+        /// chain first: those counterparties refuse transfers of expiring or teleported names.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<Timestamp>,
+        #[serde(default, skip_serializing_if = "TransferMode::is_escrow")]
+        mode: TransferMode,
     },
     ReturnName {
         collection: String,
         token_id: String,
         sender_addr: String,
         receiver_addr: String,
+        /// Where to send the name or voucher back when the packet fails. Defaults to `sender_addr`.
+        /// Left out when `None`, counterparties without it refuse packets carrying it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        refund_addr: Option<String>,
+    },
+    /// Asks the origin chain for the name data, answered in the acknowledgement.
+    ResolveName {
+        collection: String,
+        token_id: String,
+        /// Contract receiving the `ResolveNameCallbackMsg`.
+        requester: String,
+    },
+    /// Updates the records of a name escrowed on the origin chain, sent by the voucher owner.
+    UpdateNameRecord {
+        collection: String,
+        token_id: String,
+        sender_addr: String,
+        token_uri: Option<String>,
+        extension: Option<JsonValue>,
     },
+    /// Registers a name on the origin chain, escrowed there for the voucher of `owner_addr`.
+    RegisterName {
+        collection: String,
+        token_id: String,
+        /// Receives the voucher on the chain that sent the packet.
+        owner_addr: String,
+    },
+    /// Renews a name escrowed on the origin chain, sent by the voucher owner.
+    RenewName {
+        collection: String,
+        token_id: String,
+        sender_addr: String,
+    },
+    /// Copies the metadata of a name escrowed on the origin chain to its voucher.
+    SyncMetadata {
+        collection: String,
+        token_id: String,
+        token_uri: Option<String>,
+        extension: Option<JsonValue>,
+    },
+    /// Releases a name escrowed on the origin chain past its expiry.
+    ExpireVoucher {
+        collection: String,
+        token_id: String,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum TransferMode {
+    /// The name is escrowed and a voucher is minted for it.
+    #[default]
+    Escrow,
+    /// The name is burned and minted with the same token id in the remote collection.
+    Teleport {
+        token_uri: Option<String>,
+        extension: Option<JsonValue>,
+    },
+}
+
+impl TransferMode {
+    pub fn is_escrow(&self) -> bool {
+        matches!(self, TransferMode::Escrow)
+    }
+}
+
+/// Data of a successful acknowledgement.
+#[cw_serde]
+pub enum AckSuccess {
+    /// A voucher was minted for the name transferred.
+    VoucherMinted {
+        voucher_collection: String,
+        voucher_token_id: String,
+    },
+    /// The name teleported was minted in `collection`.
+    NameTeleported {
+        collection: String,
+        token_id: String,
+    },
+    /// The name returned was released from escrow.
+    NameUnescrowed {
+        recipient: String,
+    },
+    NameResolved(NameResolution),
+    NameRecordUpdated {},
+    /// The name was minted and escrowed, a voucher can be minted for it.
+    NameRegistered {},
+    NameRenewed {},
+    MetadataSynced {},
+    /// The expired name was released from escrow, the voucher can be burned.
+    NameExpired {},
+}
+
+#[cw_serde]
+pub struct NameResolution {
+    pub owner: String,
+    pub token_uri: Option<String>,
+    pub extension: Option<JsonValue>,
+}
+
+/// Any JSON value but floats, e.g. the extension of a name, carried as is whatever the type the
+/// collection gives it.
+#[cw_serde]
+#[serde(untagged)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+/// Message executed on the contract that asked for a name resolution, once it is answered.
+#[cw_serde]
+pub enum ResolveNameCallbackMsg {
+    ResolveNameCallback {
+        channel_id: String,
+        collection: String,
+        token_id: String,
+        result: ResolveNameResult,
+    },
+}
+
+#[cw_serde]
+pub enum ResolveNameResult {
+    Resolved(NameResolution),
+    Failed { error: String },
+}
+
+/// Error of a failed acknowledgement, serialized as the `StdAck` error string.
+#[cw_serde]
+pub struct AckError {
+    /// See `ContractError::code`.
+    pub code: u32,
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+#[cw_serde]
+pub enum ErrorCategory {
+    /// The packet will fail again.
+    Permanent,
+    /// The packet may succeed if sent again later, e.g. once a rate limit window is over.
+    Retriable,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Permanent => "permanent",
+            ErrorCategory::Retriable => "retriable",
+        }
+    }
 }
@@ -0,0 +1,92 @@
+use crate::{
+    error::ContractError,
+    msg::RateLimit,
+    state::{RateWindow, CHANNEL_CONFIGS, RATE_LIMIT_WINDOWS, SENDER_RATE_LIMIT_WINDOWS},
+};
+use cosmwasm_std::{Addr, Storage, Timestamp};
+
+const INBOUND: &str = "inbound";
+const OUTBOUND: &str = "outbound";
+const OUTBOUND_PER_SENDER: &str = "outbound-per-sender";
+
+pub fn consume_outbound(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    channel_id: &str,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    let Some(rate_limit) = load_rate_limit(storage, channel_id)? else {
+        return Ok(());
+    };
+    if let Some(max_outbound) = rate_limit.max_outbound {
+        let window = RATE_LIMIT_WINDOWS.may_load(storage, (channel_id, OUTBOUND))?;
+        let window = take_slot(window, now, &rate_limit, max_outbound)
+            .ok_or_else(|| rate_limited(channel_id, OUTBOUND))?;
+        RATE_LIMIT_WINDOWS.save(storage, (channel_id, OUTBOUND), &window)?;
+    }
+    if let Some(max_per_sender) = rate_limit.max_outbound_per_sender {
+        let window = SENDER_RATE_LIMIT_WINDOWS.may_load(storage, (channel_id, sender))?;
+        let window = take_slot(window, now, &rate_limit, max_per_sender)
+            .ok_or_else(|| rate_limited(channel_id, OUTBOUND_PER_SENDER))?;
+        SENDER_RATE_LIMIT_WINDOWS.save(storage, (channel_id, sender), &window)?;
+    }
+    Ok(())
+}
+
+pub fn consume_inbound(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    channel_id: &str,
+) -> Result<(), ContractError> {
+    let Some(rate_limit) = load_rate_limit(storage, channel_id)? else {
+        return Ok(());
+    };
+    if let Some(max_inbound) = rate_limit.max_inbound {
+        let window = RATE_LIMIT_WINDOWS.may_load(storage, (channel_id, INBOUND))?;
+        let window = take_slot(window, now, &rate_limit, max_inbound)
+            .ok_or_else(|| rate_limited(channel_id, INBOUND))?;
+        RATE_LIMIT_WINDOWS.save(storage, (channel_id, INBOUND), &window)?;
+    }
+    Ok(())
+}
+
+fn load_rate_limit(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> Result<Option<RateLimit>, ContractError> {
+    Ok(CHANNEL_CONFIGS
+        .may_load(storage, channel_id)?
+        .and_then(|config| config.rate_limit))
+}
+
+/// Counts one more transfer in the window, starting a new window when the current one is over.
+/// Returns `None` when the window is already full.
+fn take_slot(
+    window: Option<RateWindow>,
+    now: Timestamp,
+    rate_limit: &RateLimit,
+    max: u64,
+) -> Option<RateWindow> {
+    let window = match window {
+        Some(window) if window.started_at.plus_seconds(rate_limit.window_seconds) > now => window,
+        _ => RateWindow {
+            started_at: now,
+            count: 0,
+        },
+    };
+    if window.count >= max {
+        None
+    } else {
+        Some(RateWindow {
+            started_at: window.started_at,
+            count: window.count + 1,
+        })
+    }
+}
+
+fn rate_limited(channel_id: &str, scope: &str) -> ContractError {
+    ContractError::RateLimited {
+        channel_id: channel_id.to_owned(),
+        scope: scope.to_owned(),
+    }
+}
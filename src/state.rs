@@ -1,3 +1,231 @@
-use cw_storage_plus::Item;
+use crate::msg::{
+    ChannelConfig, ExpiryPolicy, JsonValue, RelayerFee, RenewalConfig, VoucherIdScheme,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Empty, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Deque, Item, Map};
 
 pub const VOUCHERS_ADDR: Item<String> = Item::new("vouchers_addr");
+pub const CHANNEL_CONFIGS: Map<&str, ChannelConfig> = Map::new("channel_configs");
+/// Refunds dispatched as submessages, in dispatch order, waiting for their reply.
+pub const PENDING_REFUNDS: Deque<Refund> = Deque::new("pending_refunds");
+/// Refunds that failed, keyed by (collection, token_id), with the recipient allowed to claim them.
+pub const CLAIMABLE_REFUNDS: Map<(&str, &str), String> = Map::new("claimable_refunds");
+/// Names of `CLAIMABLE_REFUNDS` that were teleported, minted again rather than transferred when
+/// claimed.
+pub const CLAIMABLE_REMINTS: Map<(&str, &str), Remint> = Map::new("claimable_remints");
+/// Packets not yet acknowledged nor timed out, keyed by (channel_id, collection, token_id).
+pub const IN_FLIGHT: Map<(&str, &str, &str), InFlight> = Map::new("in_flight");
+/// Packets sent before this time, by a version that kept no in-flight records, have none.
+pub const LEGACY_PACKETS_BEFORE: Item<Timestamp> = Item::new("legacy_packets_before");
+/// Names held in escrow by this contract, keyed by (channel_id, collection, token_id).
+pub const ESCROWED_NAMES: Map<(&str, &str, &str), Escrow> = Map::new("escrowed_names");
+/// Vouchers minted by this contract and not yet burned, keyed by voucher token id.
+pub const VOUCHER_TRACES: Map<&str, VoucherTrace> = Map::new("voucher_traces");
+/// Vouchers held in the inbox of their channel until claimed, keyed by voucher token id.
+pub const PENDING_CLAIMS: Map<&str, PendingClaim> = Map::new("pending_claims");
+/// Ids of the vouchers in `VOUCHER_TRACES`, keyed by (channel_id, collection, token_id) of their
+/// name. Vouchers minted before this index have no entry, their id is the hash.
+pub const VOUCHER_IDS: Map<(&str, &str, &str), String> = Map::new("voucher_ids");
+/// Form of the ids of new vouchers per (channel_id, collection), `VoucherIdScheme::Hash` when not
+/// set.
+pub const VOUCHER_ID_SCHEMES: Map<(&str, &str), VoucherIdScheme> = Map::new("voucher_id_schemes");
+/// Names escrowed per (channel_id, collection), kept in step with `ESCROWED_NAMES`.
+pub const ESCROWED_SUPPLY: Map<(&str, &str), u64> = Map::new("escrowed_supply");
+/// Vouchers outstanding per (channel_id, origin collection), kept in step with `VOUCHER_TRACES`.
+pub const VOUCHER_SUPPLY: Map<(&str, &str), u64> = Map::new("voucher_supply");
+/// Current rate limit windows, keyed by (channel_id, direction).
+pub const RATE_LIMIT_WINDOWS: Map<(&str, &str), RateWindow> = Map::new("rate_limit_windows");
+/// Current outbound rate limit windows, keyed by (channel_id, sender).
+pub const SENDER_RATE_LIMIT_WINDOWS: Map<(&str, &Addr), RateWindow> =
+    Map::new("sender_rate_limit_windows");
+/// Fee due for transfers and returns when neither the collection nor the channel sets one.
+pub const FLAT_FEE: Item<Coin> = Item::new("flat_fee");
+/// Fees due for transfers and returns of a collection, overriding channel and flat fees.
+pub const COLLECTION_FEES: Map<&str, Coin> = Map::new("collection_fees");
+/// Fees of delivered packets not yet withdrawn, keyed by denom.
+pub const COLLECTED_FEES: Map<&str, Uint128> = Map::new("collected_fees");
+/// Cw20 tokens accepted as fees, with the amount due.
+pub const CW20_FEES: Map<&str, Uint128> = Map::new("cw20_fees");
+/// Cw20 fees of delivered packets not yet withdrawn, keyed by cw20 contract.
+pub const COLLECTED_CW20_FEES: Map<&str, Uint128> = Map::new("collected_cw20_fees");
+/// Fee refunds dispatched as submessages, in dispatch order, waiting for their reply.
+pub const PENDING_FEE_REFUNDS: Deque<PaidFee> = Deque::new("pending_fee_refunds");
+/// Fee refunds that failed, keyed by (payer, denom), until claimed by the payer.
+pub const CLAIMABLE_FEES: Map<(&str, &str), Uint128> = Map::new("claimable_fees");
+/// Cw20 fee refunds that failed, keyed by (payer, cw20 contract), until claimed by the payer.
+pub const CLAIMABLE_CW20_FEES: Map<(&str, &str), Uint128> = Map::new("claimable_cw20_fees");
+/// Address receiving the withdrawn fees.
+pub const TREASURY: Item<String> = Item::new("treasury");
+/// Closed channels, with the time of closure.
+pub const CLOSED_CHANNELS: Map<&str, Timestamp> = Map::new("closed_channels");
+/// Channels this contract has asked to close, with the time of the request.
+pub const CLOSE_REQUESTS: Map<&str, Timestamp> = Map::new("close_requests");
+/// Counterparties allowed to open channels, keyed by (connection_id, counterparty port_id).
+pub const TRUSTED_COUNTERPARTIES: Map<(&str, &str), Empty> = Map::new("trusted_counterparties");
+/// Whether any counterparty may open channels while none is trusted.
+pub const ANY_COUNTERPARTY_ALLOWED: Item<bool> = Item::new("any_counterparty_allowed");
+/// Whether a counterparty may only have one open channel with this contract.
+pub const ONE_CHANNEL_PER_COUNTERPARTY: Item<bool> = Item::new("one_channel_per_counterparty");
+/// Last channel connected with each counterparty, keyed by (connection_id, counterparty port_id).
+pub const COUNTERPARTY_CHANNELS: Map<(&str, &str), String> = Map::new("counterparty_channels");
+/// Collections whose names may be registered from other chains.
+pub const REGISTRABLE_COLLECTIONS: Map<&str, Empty> = Map::new("registrable_collections");
+/// Renewals of names from other chains allowed per collection.
+pub const RENEWAL_CONFIGS: Map<&str, RenewalConfig> = Map::new("renewal_configs");
+/// Deposits paying for renewals, keyed by (collection, token_id, depositor), each in the denom it
+/// was made in.
+pub const RENEWAL_DEPOSITS: Map<(&str, &str, &str), Coin> = Map::new("renewal_deposits");
+/// Funds paying for prefunded renewals, keyed by denom, kept apart from fees and deposits.
+pub const RENEWAL_FUNDS: Map<&str, Uint128> = Map::new("renewal_funds");
+/// What happens to expired names per collection, `ExpiryPolicy::ReturnToSender` when not set.
+pub const EXPIRY_POLICIES: Map<&str, ExpiryPolicy> = Map::new("expiry_policies");
+/// Collections teleported, keyed by (channel_id, collection), with their counterpart on the
+/// chain at the other end.
+pub const TELEPORT_COLLECTIONS: Map<(&str, &str), String> = Map::new("teleport_collections");
+/// Collections minting teleported names, keyed by (channel_id, counterpart on the chain at the
+/// other end).
+pub const TELEPORT_REMOTES: Map<(&str, &str), String> = Map::new("teleport_remotes");
+
+#[cw_serde]
+pub struct Refund {
+    pub contract_addr: String,
+    pub token_id: String,
+    pub recipient: String,
+    /// Set when the name was teleported and is minted again instead of transferred.
+    #[serde(default)]
+    pub remint: Option<Remint>,
+}
+
+/// Metadata a teleported name is minted again with.
+#[cw_serde]
+pub struct Remint {
+    pub token_uri: Option<String>,
+    pub extension: Option<JsonValue>,
+}
+
+#[cw_serde]
+pub enum EscrowedToken {
+    Name,
+    Voucher,
+    /// Nothing is escrowed, the name was burned and is minted again on refund.
+    Teleported {
+        token_uri: Option<String>,
+        extension: Option<JsonValue>,
+    },
+}
+
+#[cw_serde]
+pub struct InFlight {
+    pub token: EscrowedToken,
+    pub refund_addr: String,
+    pub sent_at: Timestamp,
+    pub fee: Option<PaidFee>,
+    pub relayer_fee: Option<PaidRelayerFee>,
+}
+
+#[cw_serde]
+pub struct PaidFee {
+    pub payer: String,
+    pub amount: FeeAmount,
+}
+
+#[cw_serde]
+pub struct PaidRelayerFee {
+    pub payer: String,
+    pub fee: RelayerFee,
+}
+
+#[cw_serde]
+pub enum FeeAmount {
+    Native(Coin),
+    Cw20 { cw20_addr: String, amount: Uint128 },
+}
+
+#[cw_serde]
+pub struct Escrow {
+    pub sender_addr: String,
+}
+
+#[cw_serde]
+pub struct PendingClaim {
+    pub receiver_addr: String,
+    /// Address on the origin chain the name is released to when the voucher is not claimed.
+    pub return_addr: String,
+    pub claim_until: Timestamp,
+}
+
+#[cw_serde]
+pub struct RateWindow {
+    pub started_at: Timestamp,
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct VoucherTrace {
+    pub channel_id: String,
+    pub collection: String,
+    pub token_id: String,
+    pub expires_at: Option<Timestamp>,
+}
+
+pub fn save_escrowed_name(
+    storage: &mut dyn Storage,
+    key: (&str, &str, &str),
+    escrow: &Escrow,
+) -> StdResult<()> {
+    if !ESCROWED_NAMES.has(storage, key) {
+        ESCROWED_SUPPLY.update(storage, (key.0, key.1), |count| step_supply(count, true))?;
+    }
+    ESCROWED_NAMES.save(storage, key, escrow)
+}
+
+pub fn remove_escrowed_name(storage: &mut dyn Storage, key: (&str, &str, &str)) -> StdResult<()> {
+    if ESCROWED_NAMES.has(storage, key) {
+        ESCROWED_NAMES.remove(storage, key);
+        ESCROWED_SUPPLY.update(storage, (key.0, key.1), |count| step_supply(count, false))?;
+    }
+    Ok(())
+}
+
+pub fn save_voucher_trace(
+    storage: &mut dyn Storage,
+    voucher_token_id: &str,
+    trace: &VoucherTrace,
+) -> StdResult<()> {
+    if !VOUCHER_TRACES.has(storage, voucher_token_id) {
+        VOUCHER_SUPPLY.update(storage, (&trace.channel_id, &trace.collection), |count| {
+            step_supply(count, true)
+        })?;
+    }
+    VOUCHER_IDS.save(
+        storage,
+        (&trace.channel_id, &trace.collection, &trace.token_id),
+        &voucher_token_id.to_owned(),
+    )?;
+    VOUCHER_TRACES.save(storage, voucher_token_id, trace)
+}
+
+pub fn remove_voucher_trace(storage: &mut dyn Storage, voucher_token_id: &str) -> StdResult<()> {
+    if let Some(trace) = VOUCHER_TRACES.may_load(storage, voucher_token_id)? {
+        VOUCHER_TRACES.remove(storage, voucher_token_id);
+        PENDING_CLAIMS.remove(storage, voucher_token_id);
+        VOUCHER_IDS.remove(
+            storage,
+            (&trace.channel_id, &trace.collection, &trace.token_id),
+        );
+        VOUCHER_SUPPLY.update(storage, (&trace.channel_id, &trace.collection), |count| {
+            step_supply(count, false)
+        })?;
+    }
+    Ok(())
+}
+
+fn step_supply(count: Option<u64>, increase: bool) -> StdResult<u64> {
+    let count = count.unwrap_or_default();
+    Ok(if increase {
+        count + 1
+    } else {
+        count.saturating_sub(1)
+    })
+}